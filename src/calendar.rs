@@ -0,0 +1,232 @@
+//! Dates on which notifications are not shown, e.g. public holidays.
+//!
+//! A calendar is read from a text file with one date per line or from an
+//! iCalendar (`.ics`) file. In a text file a line is a date such as
+//! `2026-12-25` or an inclusive range such as `2026-12-24..2026-12-31`,
+//! anything after it is a description and lines starting with `#` are
+//! comments.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use chrono::{Datelike, NaiveDate};
+
+/// The most days in a row that are skipped before giving up, so a calendar
+/// that excludes every day doesn't hang the scheduler.
+pub(crate) const MAX_SKIPPED_DAYS: usize = 3660;
+
+/// A set of excluded dates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Calendar {
+  dates: BTreeSet<NaiveDate>,
+  /// Dates that are excluded every year from the year they start in
+  yearly: BTreeSet<NaiveDate>,
+}
+
+impl Calendar {
+  /// Reads the calendar at `path`, an `.ics` file is read as an iCalendar.
+  pub fn load(path: &Path) -> Result<Self, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let is_ics = path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .is_some_and(|ext| ext.eq_ignore_ascii_case("ics"));
+    match is_ics {
+      true => Self::parse_ics(&content),
+      false => Self::parse_dates(&content),
+    }
+  }
+
+  /// Parses a file with one date or range of dates per line.
+  pub fn parse_dates(content: &str) -> Result<Self, String> {
+    let mut calendar = Self::default();
+    for (number, line) in content.lines().enumerate() {
+      let line = line.trim();
+      let Some(token) = line.split_whitespace().next() else {
+        continue;
+      };
+      if token.starts_with('#') {
+        continue;
+      }
+      let parse = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+          format!(
+            "line {}: '{}' is not a date like 2026-12-25",
+            number + 1,
+            date
+          )
+        })
+      };
+      match token.split_once("..") {
+        Some((start, end)) => calendar.add_range(parse(start)?, parse(end)?),
+        None => {
+          calendar.dates.insert(parse(token)?);
+        }
+      }
+    }
+    Ok(calendar)
+  }
+
+  /// Parses the all day events of an iCalendar. `RRULE:FREQ=YEARLY` events
+  /// are excluded every year, other recurrence rules are ignored and only the
+  /// first occurrence is excluded.
+  pub fn parse_ics(content: &str) -> Result<Self, String> {
+    let mut calendar = Self::default();
+    let mut event: Option<IcsEvent> = None;
+    for line in unfold(content) {
+      let Some((name, value)) = line.split_once(':') else {
+        continue;
+      };
+      let (name, params) = name.split_once(';').unwrap_or((name, ""));
+      match (name.to_ascii_uppercase().as_str(), &mut event) {
+        ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+          event = Some(IcsEvent::default());
+        }
+        ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+          if let Some(event) = event.take() {
+            calendar.add_event(event)?;
+          }
+        }
+        ("DTSTART", Some(event)) => event.start = Some(ics_date(value)?),
+        ("DTEND", Some(event)) => {
+          // An end date is exclusive, an end time is on the last day
+          let all_day = value.len() == 8 || params.to_ascii_uppercase().contains("VALUE=DATE");
+          let end = ics_date(value)?;
+          event.end = Some(match all_day {
+            true => end.pred_opt().unwrap_or(end),
+            false => end,
+          });
+        }
+        ("RRULE", Some(event)) => {
+          event.yearly = value
+            .split(';')
+            .any(|part| part.eq_ignore_ascii_case("FREQ=YEARLY"));
+        }
+        _ => {}
+      }
+    }
+    Ok(calendar)
+  }
+
+  /// Whether notifications are not shown on `date`.
+  pub fn contains(&self, date: NaiveDate) -> bool {
+    self.dates.contains(&date)
+      || self
+        .yearly
+        .iter()
+        .any(|start| *start <= date && start.month() == date.month() && start.day() == date.day())
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.dates.is_empty() && self.yearly.is_empty()
+  }
+
+  /// Adds the dates of `other`.
+  pub fn extend(&mut self, other: &Calendar) {
+    self.dates.extend(other.dates.iter().copied());
+    self.yearly.extend(other.yearly.iter().copied());
+  }
+
+  fn add_range(&mut self, start: NaiveDate, end: NaiveDate) {
+    self.dates.extend(
+      start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .take(MAX_SKIPPED_DAYS),
+    );
+  }
+
+  fn add_event(&mut self, event: IcsEvent) -> Result<(), String> {
+    let start = event
+      .start
+      .ok_or_else(|| "an event has no DTSTART".to_string())?;
+    let end = event.end.unwrap_or(start).max(start);
+    match event.yearly {
+      true => self
+        .yearly
+        .extend(start.iter_days().take_while(|date| *date <= end).take(366)),
+      false => self.add_range(start, end),
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default)]
+struct IcsEvent {
+  start: Option<NaiveDate>,
+  /// The last day of the event
+  end: Option<NaiveDate>,
+  yearly: bool,
+}
+
+/// The lines of an iCalendar with the continuation lines joined.
+fn unfold(content: &str) -> Vec<String> {
+  let mut lines: Vec<String> = Vec::new();
+  for line in content.lines() {
+    match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+      (Some(rest), Some(last)) => last.push_str(rest),
+      _ => lines.push(line.trim_end().to_string()),
+    }
+  }
+  lines
+}
+
+/// The date of an iCalendar `DATE` or `DATE-TIME` value.
+fn ics_date(value: &str) -> Result<NaiveDate, String> {
+  value
+    .get(..8)
+    .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+    .ok_or_else(|| format!("'{}' is not an iCalendar date", value))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn date(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn test_parse_dates() {
+    let calendar = Calendar::parse_dates(
+      "# Public holidays\n2026-12-25 Christmas Day\n\n2026-12-30..2027-01-01 New year\n",
+    )
+    .unwrap();
+    assert!(calendar.contains(date("2026-12-25")));
+    assert!(!calendar.contains(date("2026-12-26")));
+    assert!(calendar.contains(date("2026-12-31")));
+    assert!(calendar.contains(date("2027-01-01")));
+    assert!(!calendar.contains(date("2027-01-02")));
+
+    let err = Calendar::parse_dates("2026-12-25\n25/12/2026\n").unwrap_err();
+    assert!(err.starts_with("line 2:"), "{}", err);
+  }
+
+  #[test]
+  fn test_parse_ics() {
+    let calendar = Calendar::parse_ics(
+      "BEGIN:VCALENDAR\r\n\
+       BEGIN:VEVENT\r\n\
+       SUMMARY:Christmas\r\n\
+       DTSTART;VALUE=DATE:20241225\r\n\
+       DTEND;VALUE=DATE:20241227\r\n\
+       RRULE:FREQ=YEARLY\r\n\
+       END:VEVENT\r\n\
+       BEGIN:VEVENT\r\n\
+       SUMMARY:Team off\r\n\
+       \x20site\r\n\
+       DTSTART:20260706T090000Z\r\n\
+       DTEND:20260707T170000Z\r\n\
+       END:VEVENT\r\n\
+       END:VCALENDAR\r\n",
+    )
+    .unwrap();
+    assert!(calendar.contains(date("2026-12-25")));
+    assert!(calendar.contains(date("2026-12-26")));
+    assert!(!calendar.contains(date("2026-12-27")));
+    assert!(!calendar.contains(date("2023-12-25")));
+    assert!(calendar.contains(date("2026-07-06")));
+    assert!(calendar.contains(date("2026-07-07")));
+    assert!(!calendar.contains(date("2026-07-08")));
+  }
+}
@@ -0,0 +1,154 @@
+//! The file formats the config can be written in.
+//!
+//! All formats are read into a [`serde_yaml::Value`] first so the migrations
+//! work the same regardless of the format of the file. TOML dates and times
+//! are read as strings, the way they are written in the other formats.
+
+use std::path::Path;
+
+use serde_yaml::Value;
+
+use crate::{yaml::YamlErrors, Notifications};
+
+/// A config file format, picked by the file's extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  Yaml,
+  Toml,
+  Json,
+}
+
+impl Format {
+  /// All formats, the first one is the default.
+  pub const ALL: [Format; 3] = [Format::Yaml, Format::Toml, Format::Json];
+
+  /// The format of `path` based on its extension. Unknown extensions are
+  /// treated as YAML.
+  pub fn from_path(path: &Path) -> Format {
+    match path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .map(|ext| ext.to_lowercase())
+      .as_deref()
+    {
+      Some("toml") => Format::Toml,
+      Some("json") => Format::Json,
+      _ => Format::Yaml,
+    }
+  }
+
+  /// The extension used for files of this format.
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Format::Yaml => "yaml",
+      Format::Toml => "toml",
+      Format::Json => "json",
+    }
+  }
+
+  /// Parses `content` into an untyped document.
+  pub fn parse(&self, content: &str) -> Result<Value, YamlErrors> {
+    match self {
+      Format::Yaml => serde_yaml::from_str(content).map_err(invalid),
+      Format::Toml => {
+        let table: toml::Table = toml::from_str(content).map_err(invalid)?;
+        serde_yaml::to_value(dates_as_strings(toml::Value::Table(table))).map_err(invalid)
+      }
+      Format::Json => serde_json::from_str(content).map_err(invalid),
+    }
+  }
+
+  /// Serialises the config in this format.
+  pub fn serialise(&self, notify: &Notifications) -> Result<String, YamlErrors> {
+    match self {
+      Format::Yaml => serde_yaml::to_string(notify).map_err(|_| YamlErrors::CouldNotSaveToFile),
+      Format::Toml => toml::to_string_pretty(notify).map_err(|_| YamlErrors::CouldNotSaveToFile),
+      Format::Json => serde_json::to_string_pretty(notify)
+        .map(|json| json + "\n")
+        .map_err(|_| YamlErrors::CouldNotSaveToFile),
+    }
+  }
+}
+
+/// Replaces the TOML dates and times in `value` with their text, serde
+/// can't read them into the chrono types otherwise.
+fn dates_as_strings(value: toml::Value) -> toml::Value {
+  match value {
+    toml::Value::Datetime(datetime) => toml::Value::String(datetime.to_string()),
+    toml::Value::Array(items) => {
+      toml::Value::Array(items.into_iter().map(dates_as_strings).collect())
+    }
+    toml::Value::Table(table) => toml::Value::Table(
+      table
+        .into_iter()
+        .map(|(key, value)| (key, dates_as_strings(value)))
+        .collect(),
+    ),
+    value => value,
+  }
+}
+
+/// The error for a config that couldn't be parsed. It isn't logged here as
+/// configs that are edited are parsed again to check them.
+pub(crate) fn invalid(err: impl std::fmt::Display) -> YamlErrors {
+  YamlErrors::CouldNotDeserializeFile(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Level, NotificationDetails, Routine, RoutineStep};
+
+  #[test]
+  fn test_round_trips_every_format() {
+    let notify = Notifications {
+      notifications: vec![NotificationDetails {
+        label: "Stretch".to_string(),
+        cron: "0 0 7-15 * * 1-5 *".to_string(),
+        level: Level::Warning,
+        ..Default::default()
+      }],
+      routines: vec![Routine {
+        name: "Morning stretch".to_string(),
+        steps: vec![RoutineStep {
+          label: "Neck rolls".to_string(),
+          seconds: 30,
+        }],
+        ..Default::default()
+      }],
+      ..Default::default()
+    };
+    for format in Format::ALL {
+      let content = format.serialise(&notify).unwrap();
+      let parsed: Notifications = serde_yaml::from_value(format.parse(&content).unwrap()).unwrap();
+      assert_eq!(parsed, notify, "{:?}", format);
+    }
+  }
+
+  #[test]
+  fn test_reads_unquoted_toml_dates() {
+    let content = "[[notifications]]\nlabel = \"Dentist\"\nat = 2026-11-03T14:00:00\nstart = 2026-07-01\n\n[[countdowns]]\nlabel = \"Release\"\ndate = 2026-12-01\n";
+    let notify: Notifications =
+      serde_yaml::from_value(Format::Toml.parse(content).unwrap()).unwrap();
+    let dentist = &notify.notifications[0];
+    assert_eq!(dentist.at, Some("2026-11-03T14:00:00".parse().unwrap()));
+    assert_eq!(dentist.start, Some("2026-07-01".parse().unwrap()));
+    assert_eq!(notify.countdowns[0].date, "2026-12-01".parse().unwrap());
+
+    let content = Format::Toml.serialise(&notify).unwrap();
+    let parsed: Notifications =
+      serde_yaml::from_value(Format::Toml.parse(&content).unwrap()).unwrap();
+    assert_eq!(parsed, notify);
+  }
+
+  #[test]
+  fn test_format_from_extension() {
+    assert_eq!(
+      Format::from_path(Path::new("a/notifier.TOML")),
+      Format::Toml
+    );
+    assert_eq!(Format::from_path(Path::new("notifier.json")), Format::Json);
+    assert_eq!(Format::from_path(Path::new("notifier.yml")), Format::Yaml);
+    assert_eq!(Format::from_path(Path::new("notifier")), Format::Yaml);
+  }
+}
@@ -0,0 +1,275 @@
+//! An append-only log of what happened to each notification.
+//!
+//! Every line of the history file is one JSON encoded [`HistoryEntry`], so a
+//! crash while writing only loses the last entry.
+
+use std::{
+  fmt,
+  fs::{self, OpenOptions},
+  io::Write,
+  path::PathBuf,
+  str::FromStr,
+};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What happened to a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+  /// The notification was shown
+  Shown,
+  /// The notification couldn't be shown
+  Failed,
+  /// The notification was snoozed
+  Snoozed,
+  /// The notification was closed, only reported on Linux and macOS
+  Dismissed,
+  /// The reminder was marked as done
+  Done,
+  /// The notification was held back until the quiet time was over
+  Deferred,
+  /// The notification was due during the quiet time and not shown
+  Dropped,
+}
+
+impl Outcome {
+  pub const ALL: [Outcome; 7] = [
+    Outcome::Shown,
+    Outcome::Failed,
+    Outcome::Snoozed,
+    Outcome::Dismissed,
+    Outcome::Done,
+    Outcome::Deferred,
+    Outcome::Dropped,
+  ];
+}
+
+impl fmt::Display for Outcome {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Outcome::Shown => "shown",
+      Outcome::Failed => "failed",
+      Outcome::Snoozed => "snoozed",
+      Outcome::Dismissed => "dismissed",
+      Outcome::Done => "done",
+      Outcome::Deferred => "deferred",
+      Outcome::Dropped => "dropped",
+    };
+    f.write_str(name)
+  }
+}
+
+impl FromStr for Outcome {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Outcome::ALL
+      .into_iter()
+      .find(|outcome| outcome.to_string().eq_ignore_ascii_case(s.trim()))
+      .ok_or_else(|| {
+        format!(
+          "'{}' is not one of shown, failed, snoozed, dismissed, done, deferred or dropped",
+          s
+        )
+      })
+  }
+}
+
+/// A line of the history file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+  pub at: DateTime<Utc>,
+  pub label: String,
+  pub outcome: Outcome,
+  /// Why the notification failed or how long it was snoozed for
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub detail: Option<String>,
+}
+
+impl HistoryEntry {
+  pub fn new(label: impl Into<String>, outcome: Outcome) -> Self {
+    Self {
+      at: Utc::now(),
+      label: label.into(),
+      outcome,
+      detail: None,
+    }
+  }
+
+  pub fn detail(mut self, detail: impl Into<String>) -> Self {
+    self.detail = Some(detail.into());
+    self
+  }
+}
+
+/// The entry in the system's time zone, e.g.
+/// `2026-07-01 14:00:02  shown      Stretch`.
+impl fmt::Display for HistoryEntry {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}  {:<9}  {}",
+      self.at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+      self.outcome.to_string(),
+      self.label
+    )?;
+    match &self.detail {
+      Some(detail) => write!(f, " ({})", detail),
+      None => Ok(()),
+    }
+  }
+}
+
+/// The history file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+  path: PathBuf,
+}
+
+impl History {
+  /// The name of the history file in the state directory.
+  pub const FILE_NAME: &'static str = "history.jsonl";
+
+  pub fn new(path: PathBuf) -> Self {
+    Self { path }
+  }
+
+  /// Adds an entry to the end of the history file.
+  pub fn append(&self, entry: &HistoryEntry) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+    line.push('\n');
+    OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)?
+      .write_all(line.as_bytes())
+  }
+
+  /// All entries, oldest first. A missing file is an empty history and lines
+  /// that can't be read are skipped.
+  pub fn read(&self) -> std::io::Result<Vec<HistoryEntry>> {
+    let content = match fs::read_to_string(&self.path) {
+      Ok(content) => content,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(err) => return Err(err),
+    };
+    Ok(
+      content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+          Ok(entry) => Some(entry),
+          Err(err) => {
+            tracing::warn!(path = %self.path.display(), "Skipping a history entry: {}", err);
+            None
+          }
+        })
+        .collect(),
+    )
+  }
+}
+
+/// Which history entries to show.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryFilter {
+  /// Only entries whose label contains this, ignoring case
+  pub label: Option<String>,
+  pub outcome: Option<Outcome>,
+  /// Only entries at or after this time
+  pub since: Option<DateTime<Utc>>,
+  /// Only entries before this time
+  pub until: Option<DateTime<Utc>>,
+}
+
+impl HistoryFilter {
+  pub fn matches(&self, entry: &HistoryEntry) -> bool {
+    self.label.as_ref().is_none_or(|label| {
+      entry
+        .label
+        .to_lowercase()
+        .contains(&label.trim().to_lowercase())
+    }) && self.outcome.is_none_or(|outcome| entry.outcome == outcome)
+      && self.since.is_none_or(|since| entry.at >= since)
+      && self.until.is_none_or(|until| entry.at < until)
+  }
+}
+
+/// Parses an RFC 3339 time or a date, which is the start of that day in the
+/// system's time zone.
+pub fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+  let s = s.trim();
+  if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+    return Ok(time.with_timezone(&Utc));
+  }
+  let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+    .map_err(|_| format!("'{}' is not a date like 2026-07-01 or an RFC 3339 time", s))?;
+  Local
+    .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+    .earliest()
+    .map(|time| time.with_timezone(&Utc))
+    .ok_or_else(|| format!("{} doesn't start at midnight in the system's time zone", s))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(at: &str, label: &str, outcome: Outcome) -> HistoryEntry {
+    HistoryEntry {
+      at: at.parse().unwrap(),
+      ..HistoryEntry::new(label, outcome)
+    }
+  }
+
+  #[test]
+  fn test_append_and_read() {
+    let path =
+      std::env::temp_dir().join(format!("notifier-history-{}.jsonl", uuid::Uuid::new_v4()));
+    let history = History::new(path.clone());
+    assert_eq!(history.read().unwrap(), Vec::new());
+    let shown = entry("2026-07-01T14:00:00Z", "Stretch", Outcome::Shown);
+    let failed =
+      entry("2026-07-01T15:00:00Z", "Stretch", Outcome::Failed).detail("No notification server");
+    history.append(&shown).unwrap();
+    fs::write(&path, fs::read_to_string(&path).unwrap() + "not json\n").unwrap();
+    history.append(&failed).unwrap();
+    assert_eq!(history.read().unwrap(), vec![shown, failed]);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_filter() {
+    let entry = entry("2026-07-01T14:00:00Z", "Drink water", Outcome::Shown);
+    let filter = |filter: HistoryFilter| filter.matches(&entry);
+    assert!(filter(HistoryFilter::default()));
+    assert!(filter(HistoryFilter {
+      label: Some("WATER".to_string()),
+      outcome: Some(Outcome::Shown),
+      since: Some(parse_time("2026-07-01T14:00:00Z").unwrap()),
+      until: Some(parse_time("2026-07-01T14:00:01Z").unwrap()),
+    }));
+    assert!(!filter(HistoryFilter {
+      label: Some("Stretch".to_string()),
+      ..Default::default()
+    }));
+    assert!(!filter(HistoryFilter {
+      outcome: Some(Outcome::Failed),
+      ..Default::default()
+    }));
+    assert!(!filter(HistoryFilter {
+      until: Some(parse_time("2026-07-01T14:00:00Z").unwrap()),
+      ..Default::default()
+    }));
+  }
+
+  #[test]
+  fn test_parse_time_and_outcome() {
+    let midnight = Local.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap();
+    assert_eq!(parse_time("2026-07-01"), Ok(midnight.with_timezone(&Utc)));
+    assert!(parse_time("yesterday").is_err());
+    assert_eq!("Dismissed".parse(), Ok(Outcome::Dismissed));
+    assert!("ignored".parse::<Outcome>().is_err());
+  }
+}
@@ -1,369 +1,1447 @@
-//! # JobScheduler
-//!
-//! A simple cron-like job scheduling library for Rust.
-//!
-//! ## Usage
-//!
-//! Be sure to add the job_scheduler crate to your `Cargo.toml`:
-//!
-//! ```toml
-//! [dependencies]
-//! job_scheduler = "*"
-//! ```
-//!
-//! Creating a schedule for a job is done using the `FromStr` impl for the
-//! `Schedule` type of the [cron](https://github.com/zslayton/cron) library.
-//!
-//! The scheduling format is as follows:
-//!
-//! ```text
-//! sec   min   hour   day of month   month   day of week   year
-//! *     *     *      *              *       *             *
-//! ```
-//!
-//! Note that the year may be omitted.
-//!
-//! Comma separated values such as `5,8,10` represent more than one time
-//! value. So for example, a schedule of `0 2,14,26 * * * *` would execute
-//! on the 2nd, 14th, and 26th minute of every hour.
-//!
-//! Ranges can be specified with a dash. A schedule of `0 0 * 5-10 * *`
-//! would execute once per hour but only on day 5 through 10 of the month.
-//!
-//! Day of the week can be specified as an abbreviation or the full name.
-//! A schedule of `0 0 6 * * Sun,Sat` would execute at 6am on Sunday and
-//! Saturday.
-//!
-//! A simple usage example:
-//!
-//! ```rust,ignore
-//! extern crate job_scheduler;
-//! use job_scheduler::{JobScheduler, Job};
-//! use std::time::Duration;
-//!
-//! fn main() {
-//!     let mut sched = JobScheduler::new();
-//!
-//!     sched.add(Job::new("1/10 * * * * *".parse().unwrap(), || {
-//!         println!("I get executed every 10 seconds!");
-//!     }));
-//!
-//!     loop {
-//!         sched.tick();
-//!
-//!         std::thread::sleep(Duration::from_millis(500));
-//!     }
-//! }
-//! ```
-
-extern crate chrono;
-extern crate cron;
-extern crate uuid;
-
-use chrono::{offset, DateTime, Duration, Local, TimeZone, Utc};
-pub use cron::Schedule;
-use notify_rust::Notification;
-pub use uuid::Uuid;
-
-use crate::Errors;
-
-// http://0pointer.de/public/sound-naming-spec.html
-#[cfg(all(unix, not(target_os = "macos")))]
-static SOUND: &str = "dialog-information";
-
-// https://allenbenz.github.io/winrt-notification/0_5_0/winrt_notification/enum.Sound.html
-#[cfg(target_os = "windows")]
-static SOUND: &str = "Reminder";
-
-pub trait SystemTime
-where
-  Self: TimeZone,
-{
-  /// Construct a time from a timezone
-  fn now() -> DateTime<Self>;
-}
-
-impl SystemTime for Utc {
-  fn now() -> DateTime<Utc> {
-    Utc::now()
-  }
-}
-
-impl SystemTime for Local {
-  fn now() -> DateTime<Local> {
-    Local::now()
-  }
-}
-
-/// A schedulable `Job`.
-pub struct Job {
-  schedule: Schedule,
-  last_tick: Option<DateTime<Utc>>,
-  last_tick_local: Option<DateTime<Local>>,
-  limit_missed_runs: usize,
-  label: String,
-  job_id: Uuid,
-}
-
-impl Job {
-  /// Create a new job.
-  ///
-  /// ```rust,ignore
-  /// // Run at second 0 of the 15th minute of the 6th, 8th, and 10th hour
-  /// // of any day in March and June that is a Friday of the year 2017.
-  /// let s: Schedule = "0 15 6,8,10 * Mar,Jun Fri 2017".into().unwrap();
-  /// Job::new(s, || println!("I have a complex schedule...") );
-  /// ```
-  pub fn new(schedule: Schedule, label: String) -> Job {
-    Job {
-      label,
-      schedule,
-      last_tick: None,
-      last_tick_local: None,
-      limit_missed_runs: 1,
-      job_id: Uuid::new_v4(),
-    }
-  }
-
-  #[cfg(all(unix, not(target_os = "macos")))]
-  fn display_notification(&self) -> Result<(), Errors> {
-    Notification::new()
-      .body(self.label.as_str())
-      .sound_name(SOUND)
-      .show()
-      .map_err(|e| Errors::NotificationError(e.to_string()))?
-      .wait_for_action(|action| match action {
-        _ => (),
-      });
-    Ok(())
-  }
-
-  #[cfg(target_os = "macos")]
-  fn display_notification(&self) -> Result<(), Errors> {
-    Notification::new()
-      .body(self.label.as_str())
-      .show()
-      .map_err(|e| Errors::NotificationError(e.to_string()))?
-      .wait_for_action(|action| match action {
-        _ => (),
-      });
-    Ok(())
-  }
-
-  #[cfg(target_os = "windows")]
-  fn display_notification(&self) -> Result<(), Errors> {
-    Notification::new()
-      .body(self.label.as_str())
-      .sound_name(SOUND)
-      .show()
-      .map_err(|e| Errors::NotificationError(e.to_string()))
-  }
-
-  fn tick(&mut self) {
-    let now = Utc::now();
-    if self.last_tick.is_none() {
-      self.last_tick = Some(now);
-      return;
-    }
-    if self.limit_missed_runs > 0 {
-      for event in self
-        .schedule
-        .after(&self.last_tick.unwrap())
-        .take(self.limit_missed_runs)
-      {
-        if event > now {
-          break;
-        }
-        if let Err(e) = self.display_notification() {
-          eprintln!("Error displaying notification: {}", e);
-        }
-      }
-    } else {
-      for event in self.schedule.after(&self.last_tick.unwrap()) {
-        if event > now {
-          break;
-        }
-        if let Err(e) = self.display_notification() {
-          eprintln!("Error displaying notification: {}", e);
-        }
-      }
-    }
-
-    self.last_tick = Some(now);
-  }
-
-  fn tick_with_system_time(&mut self) {
-    let now = Local::now();
-    if self.last_tick_local.is_none() {
-      self.last_tick_local = Some(now);
-      return;
-    }
-    if self.limit_missed_runs > 0 {
-      for event in self
-        .schedule
-        .after(&self.last_tick_local.unwrap())
-        .take(self.limit_missed_runs)
-      {
-        if event > now {
-          break;
-        }
-        if let Err(e) = self.display_notification() {
-          eprintln!("Error displaying notification: {}", e);
-        }
-      }
-    } else {
-      for event in self.schedule.after(&self.last_tick_local.unwrap()) {
-        if event > now {
-          break;
-        }
-        if let Err(e) = self.display_notification() {
-          eprintln!("Error displaying notification: {}", e);
-        }
-      }
-    }
-
-    self.last_tick_local = Some(now);
-  }
-
-  /// Set the limit for missed jobs in the case of delayed runs. Setting to 0 means unlimited.
-  ///
-  /// ```rust,ignore
-  /// let mut job = Job::new("0/1 * * * * *".parse().unwrap(), || {
-  ///     println!("I get executed every 1 seconds!");
-  /// });
-  /// job.limit_missed_runs(99);
-  /// ```
-  pub fn limit_missed_runs(&mut self, limit: usize) {
-    self.limit_missed_runs = limit;
-  }
-
-  /// Set last tick to force re-running of missed runs.
-  ///
-  /// ```rust,ignore
-  /// let mut job = Job::new("0/1 * * * * *".parse().unwrap(), || {
-  ///     println!("I get executed every 1 seconds!");
-  /// });
-  /// job.last_tick(Some(Utc::now()));
-  /// ```
-  pub fn last_tick(&mut self, last_tick: Option<DateTime<Utc>>) {
-    self.last_tick = last_tick;
-  }
-}
-
-#[derive(Default)]
-/// The JobScheduler contains and executes the scheduled jobs.
-pub struct JobScheduler {
-  jobs: Vec<Job>,
-}
-
-impl JobScheduler {
-  /// Create a new `JobScheduler`.
-  pub fn new() -> JobScheduler {
-    JobScheduler { jobs: Vec::new() }
-  }
-
-  /// Add a job to the `JobScheduler`
-  ///
-  /// ```rust,ignore
-  /// let mut sched = JobScheduler::new();
-  /// sched.add(Job::new("1/10 * * * * *".parse().unwrap(), || {
-  ///     println!("I get executed every 10 seconds!");
-  /// }));
-  /// ```
-  pub fn add(&mut self, job: Job) -> Uuid {
-    let job_id = job.job_id;
-    self.jobs.push(job);
-
-    job_id
-  }
-
-  /// Remove a job from the `JobScheduler`
-  ///
-  /// ```rust,ignore
-  /// let mut sched = JobScheduler::new();
-  /// let job_id = sched.add(Job::new("1/10 * * * * *".parse().unwrap(), || {
-  ///     println!("I get executed every 10 seconds!");
-  /// }));
-  /// sched.remove(job_id);
-  /// ```
-  pub fn remove(&mut self, job_id: Uuid) -> bool {
-    let mut found_index = None;
-    for (i, job) in self.jobs.iter().enumerate() {
-      if job.job_id == job_id {
-        found_index = Some(i);
-        break;
-      }
-    }
-
-    if let Some(index) = found_index {
-      self.jobs.remove(index);
-    }
-
-    found_index.is_some()
-  }
-
-  /// Remove all jobs from the `JobScheduler`
-  pub fn remove_all(&mut self) {
-    self.jobs.clear();
-  }
-
-  /// The `tick` method increments time for the JobScheduler and executes
-  /// any pending jobs. It is recommended to sleep for at least 500
-  /// milliseconds between invocations of this method.
-  ///
-  /// ```rust,ignore
-  /// loop {
-  ///     sched.tick();
-  ///     std::thread::sleep(Duration::from_millis(500));
-  /// }
-  /// ```
-  pub fn tick(&mut self) {
-    for job in &mut self.jobs {
-      job.tick();
-    }
-  }
-
-  /// The `tick_with_system_time` method increments time for the JobScheduler and executes
-  /// any pending jobs. It is recommended to sleep for at least 500
-  /// milliseconds between invocations of this method.
-  ///
-  /// ```rust,ignore
-  /// loop {
-  ///     sched.tick_with_system_time();
-  ///     std::thread::sleep(Duration::from_millis(500));
-  /// }
-  /// ```
-  pub fn tick_with_system_time(&mut self) {
-    for job in &mut self.jobs {
-      job.tick_with_system_time();
-    }
-  }
-
-  /// The `time_till_next_job` method returns the duration till the next job
-  /// is supposed to run. This can be used to sleep until then without waking
-  /// up at a fixed interval.AsMut
-  ///
-  /// ```rust, ignore
-  /// loop {
-  ///     sched.tick();
-  ///     std::thread::sleep(sched.time_till_next_job());
-  /// }
-  /// ```
-  pub fn time_till_next_job(&self) -> std::time::Duration {
-    if self.jobs.is_empty() {
-      // Take a guess if there are no jobs.
-      return std::time::Duration::from_millis(500);
-    }
-    let mut duration = Duration::zero();
-    let now = Utc::now();
-    for job in self.jobs.iter() {
-      for event in job.schedule.upcoming(offset::Utc).take(1) {
-        let d = event - now;
-        if duration.is_zero() || d < duration {
-          duration = d;
-        }
-      }
-    }
-    duration.to_std().unwrap()
-  }
-}
+//! # JobScheduler
+//!
+//! A simple cron-like job scheduling library for Rust.
+//!
+//! ## Usage
+//!
+//! Be sure to add the job_scheduler crate to your `Cargo.toml`:
+//!
+//! ```toml
+//! [dependencies]
+//! job_scheduler = "*"
+//! ```
+//!
+//! Creating a schedule for a job is done using the `FromStr` impl for the
+//! `Schedule` type of the [cron](https://github.com/zslayton/cron) library.
+//!
+//! The scheduling format is as follows:
+//!
+//! ```text
+//! sec   min   hour   day of month   month   day of week   year
+//! *     *     *      *              *       *             *
+//! ```
+//!
+//! Note that the year may be omitted.
+//!
+//! Comma separated values such as `5,8,10` represent more than one time
+//! value. So for example, a schedule of `0 2,14,26 * * * *` would execute
+//! on the 2nd, 14th, and 26th minute of every hour.
+//!
+//! Ranges can be specified with a dash. A schedule of `0 0 * 5-10 * *`
+//! would execute once per hour but only on day 5 through 10 of the month.
+//!
+//! Day of the week can be specified as an abbreviation or the full name.
+//! A schedule of `0 0 6 * * Sun,Sat` would execute at 6am on Sunday and
+//! Saturday.
+//!
+//! A simple usage example:
+//!
+//! ```rust,ignore
+//! extern crate job_scheduler;
+//! use job_scheduler::{JobScheduler, Job};
+//! use std::time::Duration;
+//!
+//! fn main() {
+//!     let mut sched = JobScheduler::new();
+//!
+//!     sched.add(Job::new("1/10 * * * * *".parse().unwrap(), || {
+//!         println!("I get executed every 10 seconds!");
+//!     }));
+//!
+//!     loop {
+//!         sched.tick();
+//!
+//!         std::thread::sleep(Duration::from_millis(500));
+//!     }
+//! }
+//! ```
+
+extern crate chrono;
+extern crate cron;
+extern crate uuid;
+
+use std::str::FromStr;
+#[cfg(not(target_os = "windows"))]
+use std::{sync::OnceLock, time::Instant};
+
+use chrono::{
+  DateTime, Datelike, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+  Utc,
+};
+use chrono_tz::Tz;
+pub use cron::Schedule;
+use notify_rust::Notification;
+pub use uuid::Uuid;
+
+use crate::{
+  calendar::{Calendar, MAX_SKIPPED_DAYS},
+  random, Errors, Escalation, Level,
+};
+
+// http://0pointer.de/public/sound-naming-spec.html
+#[cfg(all(unix, not(target_os = "macos")))]
+static SOUND: &str = "dialog-information";
+
+// https://allenbenz.github.io/winrt-notification/0_5_0/winrt_notification/enum.Sound.html
+#[cfg(target_os = "windows")]
+static SOUND: &str = "Reminder";
+
+pub trait SystemTime
+where
+  Self: TimeZone,
+{
+  /// Construct a time from a timezone
+  fn now() -> DateTime<Self>;
+}
+
+impl SystemTime for Utc {
+  fn now() -> DateTime<Utc> {
+    Utc::now()
+  }
+}
+
+impl SystemTime for Local {
+  fn now() -> DateTime<Local> {
+    Local::now()
+  }
+}
+
+/// The id of the notification action that marks a reminder as done.
+#[cfg(all(unix, not(target_os = "macos")))]
+const DONE_ACTION: &str = "done";
+
+/// What the user did with a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Response {
+  /// The reminder was marked as done from the notification
+  #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+  Done,
+  /// The notification was closed
+  Dismissed,
+  /// The platform doesn't report what happened to the notification
+  #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+  Unknown,
+}
+
+/// Shows a notification, `on_shown` is called once it is on screen. Waits for
+/// the notification to be closed on the platforms that report it.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn show_notification(
+  body: &str,
+  level: Level,
+  on_shown: impl FnOnce(),
+) -> Result<Response, Errors> {
+  let mut notification = Notification::new();
+  notification
+    .body(body)
+    .sound_name(SOUND)
+    .action(DONE_ACTION, "Done");
+  // Info is left at the default urgency so existing reminders look the same
+  if level == Level::Critical {
+    notification.urgency(notify_rust::Urgency::Critical);
+  }
+  let handle = notification
+    .show()
+    .map_err(|e| Errors::NotificationError(e.to_string()))?;
+  on_shown();
+  let mut response = Response::Dismissed;
+  handle.wait_for_action(|action| {
+    if action == DONE_ACTION {
+      response = Response::Done;
+    }
+  });
+  Ok(response)
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn show_notification(
+  body: &str,
+  _level: Level,
+  on_shown: impl FnOnce(),
+) -> Result<Response, Errors> {
+  let handle = Notification::new()
+    .body(body)
+    .show()
+    .map_err(|e| Errors::NotificationError(e.to_string()))?;
+  on_shown();
+  handle.wait_for_action(|_action| ());
+  Ok(Response::Dismissed)
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn show_notification(
+  body: &str,
+  _level: Level,
+  on_shown: impl FnOnce(),
+) -> Result<Response, Errors> {
+  Notification::new()
+    .body(body)
+    .sound_name(SOUND)
+    .show()
+    .map_err(|e| Errors::NotificationError(e.to_string()))?;
+  on_shown();
+  Ok(Response::Unknown)
+}
+
+/// How far the wall clock can drift from the awake time between two ticks
+/// before it is treated as a jump.
+const JUMP_THRESHOLD: Duration = Duration::seconds(30);
+
+/// Missed events are only counted up to this number per job.
+const MAX_COUNTED_MISSED: usize = 999;
+
+/// A change of the wall clock between two ticks that doesn't match the time
+/// that actually passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClockJump {
+  None,
+  /// The system was suspended or the clock was set forward
+  Forward(Duration),
+  /// The clock was set back
+  Backward(Duration),
+}
+
+/// How long the system was awake since an arbitrary point, this doesn't
+/// advance while the system is suspended and isn't affected by changing the
+/// system time. [`Instant`] doesn't count the time suspended on Linux and
+/// macOS.
+#[cfg(not(target_os = "windows"))]
+fn awake_time() -> std::time::Duration {
+  static START: OnceLock<Instant> = OnceLock::new();
+  START.get_or_init(Instant::now).elapsed()
+}
+
+/// How long the system was awake since it booted. [`std::time::Instant`]
+/// keeps counting while Windows is asleep, the unbiased interrupt time
+/// doesn't.
+#[cfg(target_os = "windows")]
+fn awake_time() -> std::time::Duration {
+  #[link(name = "kernel32")]
+  extern "system" {
+    fn QueryUnbiasedInterruptTime(unbiased_time: *mut u64) -> i32;
+  }
+  let mut time = 0u64;
+  // SAFETY: the pointer is to a live u64 the call only writes to
+  unsafe { QueryUnbiasedInterruptTime(&mut time) };
+  // Counted in 100 nanosecond intervals
+  std::time::Duration::from_nanos(time.saturating_mul(100))
+}
+
+/// Detects wall clock jumps by comparing how much the wall clock and the
+/// [`awake_time`] advanced between ticks. On a system whose awake time counts
+/// the time suspended a resume can't be told apart from time passing, so the
+/// missed events are shown one by one instead of being summarised.
+#[derive(Debug, Default)]
+struct ClockWatch {
+  last: Option<(DateTime<Utc>, std::time::Duration)>,
+}
+
+impl ClockWatch {
+  fn observe(&mut self, wall: DateTime<Utc>, awake: std::time::Duration) -> ClockJump {
+    let jump = match self.last {
+      Some((last_wall, last_awake)) => {
+        let elapsed = Duration::from_std(awake.saturating_sub(last_awake)).unwrap_or_default();
+        let drift = (wall - last_wall) - elapsed;
+        if drift > JUMP_THRESHOLD {
+          ClockJump::Forward(drift)
+        } else if drift < -JUMP_THRESHOLD {
+          ClockJump::Backward(-drift)
+        } else {
+          ClockJump::None
+        }
+      }
+      None => ClockJump::None,
+    };
+    self.last = Some((wall, awake));
+    jump
+  }
+}
+
+/// The text of the single notification shown for the events missed while the
+/// system was suspended, `None` if nothing was missed.
+fn summarise_missed(missed: &[(String, usize)]) -> Option<String> {
+  match missed {
+    [] => None,
+    [(label, 1)] => Some(label.clone()),
+    _ => {
+      let labels: Vec<String> = missed
+        .iter()
+        .map(|(label, count)| match count {
+          1 => label.clone(),
+          &MAX_COUNTED_MISSED => format!("{} ({}+ times)", label, MAX_COUNTED_MISSED),
+          count => format!("{} ({} times)", label, count),
+        })
+        .collect();
+      Some(format!("Missed while away: {}", labels.join(", ")))
+    }
+  }
+}
+
+/// The time zone a job's schedule is evaluated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Zone {
+  /// The system's time zone
+  #[default]
+  Local,
+  /// An IANA time zone such as `America/New_York`
+  Named(Tz),
+}
+
+impl FromStr for Zone {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim() {
+      "" | "Local" => Ok(Zone::Local),
+      name => Tz::from_str(name)
+        .map(Zone::Named)
+        .map_err(|_| Errors::InvalidTimeZone(name.to_string())),
+    }
+  }
+}
+
+/// The first instant whose wall clock time in `tz` is at or after `local`.
+///
+/// Daylight saving transitions are handled as follows:
+///
+/// - A local time that happens twice when the clocks go back resolves to its
+///   first occurrence.
+/// - A local time that is skipped when the clocks go forward resolves to the
+///   instant the clocks jump, i.e. the next valid local time.
+fn resolve<Z: TimeZone>(tz: &Z, local: NaiveDateTime) -> DateTime<Utc> {
+  match tz.from_local_datetime(&local) {
+    LocalResult::Single(time) => time.with_timezone(&Utc),
+    LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+    LocalResult::None => {
+      // UTC offsets are within +/-14 hours so the jump is within this range.
+      let base = local.and_utc().timestamp();
+      let (mut low, mut high) = (base - 15 * 3600, base + 15 * 3600);
+      let wall_clock = |timestamp| {
+        DateTime::from_timestamp(timestamp, 0)
+          .map(|time| tz.from_utc_datetime(&time.naive_utc()).naive_local())
+      };
+      while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if wall_clock(mid).is_some_and(|time| time >= local) {
+          high = mid;
+        } else {
+          low = mid;
+        }
+      }
+      DateTime::from_timestamp(high, 0).unwrap_or_default()
+    }
+  }
+}
+
+impl Zone {
+  fn naive_local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+    match self {
+      Zone::Local => time.with_timezone(&Local).naive_local(),
+      Zone::Named(tz) => time.with_timezone(tz).naive_local(),
+    }
+  }
+
+  /// The instant of the wall clock time `local` in this time zone.
+  pub(crate) fn resolve(&self, local: NaiveDateTime) -> DateTime<Utc> {
+    match self {
+      Zone::Local => resolve(&Local, local),
+      Zone::Named(tz) => resolve(tz, local),
+    }
+  }
+
+  /// The events of `schedule` after `after`, evaluated in this time zone.
+  ///
+  /// The schedule is matched against the wall clock time so daylight saving
+  /// transitions don't change when it fires. An event in an hour that repeats
+  /// when the clocks go back fires once, in the first pass through the hour.
+  /// An event in an hour that is skipped when the clocks go forward fires when
+  /// the clocks jump, multiple skipped events fire once.
+  pub fn upcoming<'a>(
+    &self,
+    schedule: &'a Schedule,
+    after: DateTime<Utc>,
+  ) -> Box<dyn Iterator<Item = DateTime<Utc>> + 'a> {
+    let zone = *self;
+    // The schedule is evaluated in UTC, which has no transitions, as a stand
+    // in for the wall clock time.
+    let wall_clock = Utc.from_utc_datetime(&zone.naive_local(after));
+    let mut last = after;
+    Box::new(
+      schedule
+        .after(&wall_clock)
+        .map(move |event| zone.resolve(event.naive_utc()))
+        .filter(move |event| {
+          let later = *event > last;
+          if later {
+            last = *event;
+          }
+          later
+        }),
+    )
+  }
+
+  /// The events of `schedule` after `after` that are not on a date in
+  /// `except`, dates are in this time zone.
+  pub fn upcoming_except<'a>(
+    &self,
+    schedule: &'a Schedule,
+    after: DateTime<Utc>,
+    except: &'a Calendar,
+  ) -> Box<dyn Iterator<Item = DateTime<Utc>> + 'a> {
+    if except.is_empty() {
+      return self.upcoming(schedule, after);
+    }
+    let zone = *self;
+    let mut after = after;
+    Box::new(std::iter::from_fn(move || {
+      for _ in 0..MAX_SKIPPED_DAYS {
+        let event = zone.upcoming(schedule, after).next()?;
+        let date = zone.naive_local(event).date();
+        if !except.contains(date) {
+          after = event;
+          return Some(event);
+        }
+        // Start again from the end of the excluded day rather than going
+        // through all of its events
+        let next_day = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+        after = (zone.resolve(next_day) - Duration::seconds(1)).max(event);
+      }
+      None
+    }))
+  }
+
+  /// Formats `time` in this time zone.
+  pub fn format(&self, time: DateTime<Utc>) -> String {
+    match self {
+      Zone::Local => time.with_timezone(&Local).to_string(),
+      Zone::Named(tz) => time.with_timezone(tz).to_string(),
+    }
+  }
+}
+
+/// A notification that is due to be shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fire {
+  /// The job that fired, `None` for the summary of missed notifications
+  pub job_id: Option<Uuid>,
+  /// The text of the notification
+  pub body: String,
+  pub level: Level,
+  /// Which repeat of an escalating notification this is, 0 for the first
+  /// time it is shown
+  pub repeat: u32,
+}
+
+/// When a job fires, apart from when it is snoozed.
+#[derive(Debug, Clone)]
+pub enum Trigger {
+  /// On the events of the schedule
+  Schedule(Box<Schedule>),
+  /// Only when it is snoozed, e.g. a follow-up is shown some time after the
+  /// notification it follows
+  Never,
+}
+
+impl From<Schedule> for Trigger {
+  fn from(schedule: Schedule) -> Self {
+    Trigger::Schedule(Box::new(schedule))
+  }
+}
+
+/// A schedulable `Job`.
+pub struct Job {
+  schedule: Trigger,
+  zone: Zone,
+  last_tick: Option<DateTime<Utc>>,
+  last_tick_local: Option<DateTime<Local>>,
+  limit_missed_runs: usize,
+  label: String,
+  job_id: Uuid,
+  paused: bool,
+  snoozed: Vec<DateTime<Utc>>,
+  level: Level,
+  escalation: Option<Escalation>,
+  /// When the repeats of an escalating notification are due
+  repeats: Vec<(DateTime<Utc>, u32)>,
+  /// The dates the job doesn't fire on
+  except: Calendar,
+  /// The first and last date the job fires on
+  starts: Option<NaiveDate>,
+  ends: Option<NaiveDate>,
+  /// The most times the job fires
+  limit: Option<u32>,
+  /// The most each event is moved earlier or later
+  jitter: Duration,
+  /// The time of day the job fires at once a day, instead of the times of the
+  /// schedule
+  window: Option<(NaiveTime, NaiveTime)>,
+  /// Seeds the jitter and the times in the window
+  seed: u64,
+  /// How many times the job fired on its schedule
+  shown: u32,
+}
+
+impl Job {
+  /// Create a new job.
+  ///
+  /// ```rust,ignore
+  /// // Run at second 0 of the 15th minute of the 6th, 8th, and 10th hour
+  /// // of any day in March and June that is a Friday of the year 2017.
+  /// let s: Schedule = "0 15 6,8,10 * Mar,Jun Fri 2017".into().unwrap();
+  /// Job::new(s, || println!("I have a complex schedule...") );
+  /// ```
+  pub fn new(schedule: impl Into<Trigger>, label: String) -> Job {
+    Job::new_with_id(schedule, label, Uuid::new_v4())
+  }
+
+  /// Create a new job with a known id, e.g. the `job_id` of a notification.
+  pub fn new_with_id(schedule: impl Into<Trigger>, label: String, job_id: Uuid) -> Job {
+    Job {
+      seed: random::seed(&label),
+      label,
+      schedule: schedule.into(),
+      zone: Zone::Local,
+      last_tick: None,
+      last_tick_local: None,
+      limit_missed_runs: 1,
+      job_id,
+      paused: false,
+      snoozed: Vec::new(),
+      level: Level::Info,
+      escalation: None,
+      repeats: Vec::new(),
+      except: Calendar::default(),
+      starts: None,
+      ends: None,
+      limit: None,
+      shown: 0,
+      jitter: Duration::zero(),
+      window: None,
+    }
+  }
+
+  fn display_notification(&self) -> Result<(), Errors> {
+    show_notification(&self.label, self.level, || ())?;
+    Ok(())
+  }
+
+  fn tick(&mut self) {
+    let now = Utc::now();
+    let (Some(last_tick), Trigger::Schedule(schedule)) = (self.last_tick, &self.schedule) else {
+      self.last_tick = Some(now);
+      return;
+    };
+    if self.limit_missed_runs > 0 {
+      for event in schedule.after(&last_tick).take(self.limit_missed_runs) {
+        if event > now {
+          break;
+        }
+        if let Err(e) = self.display_notification() {
+          tracing::error!(label = %self.label, "Error displaying notification: {}", e);
+        }
+      }
+    } else {
+      for event in schedule.after(&last_tick) {
+        if event > now {
+          break;
+        }
+        if let Err(e) = self.display_notification() {
+          tracing::error!(label = %self.label, "Error displaying notification: {}", e);
+        }
+      }
+    }
+
+    self.last_tick = Some(now);
+  }
+
+  /// Moves the job's clock to `now` and returns the events that are due since
+  /// the previous tick, including snoozed ones. The first tick only sets the
+  /// clock and a paused job never has any events due.
+  fn tick_at(&mut self, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let mut due = match self.last_tick_local {
+      Some(last_tick) => self.due(last_tick.with_timezone(&Utc), now),
+      None => Vec::new(),
+    };
+    if !self.paused {
+      self.shown += due.len() as u32;
+    }
+    due.extend(self.take_snoozed(now));
+    due.sort();
+    self.advance(now);
+    if self.paused && !due.is_empty() {
+      tracing::debug!(job_id = %self.job_id, label = %self.label, "Not showing a paused notification");
+      due.clear();
+    }
+    due
+  }
+
+  /// Moves the job's clock to `now` without showing the events that were due
+  /// and returns how many were missed.
+  fn skip_to(&mut self, now: DateTime<Utc>) -> usize {
+    let missed = match self.last_tick_local {
+      Some(last_tick) => self
+        .upcoming(last_tick.with_timezone(&Utc))
+        .take(MAX_COUNTED_MISSED)
+        .take_while(|event| *event <= now)
+        .count(),
+      None => 0,
+    };
+    if !self.paused {
+      self.shown += missed as u32;
+    }
+    let missed = (missed + self.take_snoozed(now).len()).min(MAX_COUNTED_MISSED);
+    // Repeats are reminders of something that was already shown
+    self.repeats.retain(|(at, _)| *at > now);
+    self.advance(now);
+    if self.paused {
+      0
+    } else {
+      missed
+    }
+  }
+
+  /// Schedules the repeats of an escalating notification that was shown at
+  /// `at`, replacing the repeats of the previous time it was shown.
+  fn escalate(&mut self, at: DateTime<Utc>) {
+    self.repeats = match self.escalation {
+      Some(escalation) => {
+        let every = Duration::minutes(escalation.every.max(1) as i64);
+        (1..=escalation.times)
+          .map(|repeat| (at + every * repeat as i32, repeat))
+          .collect()
+      }
+      None => Vec::new(),
+    };
+  }
+
+  /// The repeats that are due by `now`, none while the job is paused.
+  fn take_repeats(&mut self, now: DateTime<Utc>) -> Vec<u32> {
+    let (due, later): (Vec<_>, Vec<_>) = self.repeats.iter().partition(|(at, _)| *at <= now);
+    self.repeats = later;
+    if self.paused {
+      return Vec::new();
+    }
+    due.into_iter().map(|(_, repeat)| repeat).collect()
+  }
+
+  /// The level of the notification for `repeat`.
+  fn level_of(&self, repeat: u32) -> Level {
+    match self.escalation {
+      Some(escalation) if escalation.raise_urgency => {
+        (0..repeat).fold(self.level, |level, _| level.raised())
+      }
+      _ => self.level,
+    }
+  }
+
+  fn fire(&self, repeat: u32) -> Fire {
+    Fire {
+      job_id: Some(self.job_id),
+      body: self.label.clone(),
+      level: self.level_of(repeat),
+      repeat,
+    }
+  }
+
+  fn take_snoozed(&mut self, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let (due, later) = self.snoozed.iter().partition(|at| **at <= now);
+    self.snoozed = later;
+    due
+  }
+
+  /// When the job will fire next after `now`, `None` if it is paused or won't
+  /// fire again.
+  fn next_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if self.paused {
+      return None;
+    }
+    let after = self
+      .last_tick_local
+      .map_or(now, |last_tick| last_tick.with_timezone(&Utc).max(now));
+    let scheduled = self.upcoming(after).next();
+    scheduled
+      .into_iter()
+      .chain(self.snoozed.iter().copied())
+      .chain(self.repeats.iter().map(|(at, _)| *at))
+      .min()
+  }
+
+  /// The events of the schedule after `after` that are on the dates the job
+  /// fires on, until it has fired as often as its limit. Events are moved by
+  /// the job's jitter and an event that would be moved before the previous one
+  /// is left out.
+  fn upcoming(&self, after: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+    let after = match self.starts.and_then(|starts| starts.and_hms_opt(0, 0, 0)) {
+      Some(starts) => after.max(self.zone.resolve(starts) - Duration::seconds(1)),
+      None => after,
+    };
+    let remaining = self.limit.map_or(usize::MAX, |limit| {
+      limit.saturating_sub(self.shown) as usize
+    });
+    let scheduled: Box<dyn Iterator<Item = DateTime<Utc>>> = match (&self.schedule, self.window) {
+      (Trigger::Never, _) => Box::new(std::iter::empty()),
+      (Trigger::Schedule(_), Some(window)) => Box::new(self.in_window(window, after - self.jitter)),
+      (Trigger::Schedule(schedule), None) => {
+        self
+          .zone
+          .upcoming_except(schedule, after - self.jitter, &self.except)
+      }
+    };
+    let jitter = self.jitter.num_seconds();
+    let mut last = after;
+    scheduled
+      .map(move |event| {
+        event + Duration::seconds(random::jitter(self.seed, event.timestamp(), jitter))
+      })
+      .filter(move |event| {
+        let later = *event > last;
+        if later {
+          last = *event;
+        }
+        later
+      })
+      .take_while(|event| {
+        self
+          .ends
+          .is_none_or(|ends| self.zone.naive_local(*event).date() <= ends)
+      })
+      .take(remaining)
+  }
+
+  /// An event at a random time in `window` on each day the schedule has an
+  /// event on, after `after`.
+  fn in_window(
+    &self,
+    (from, to): (NaiveTime, NaiveTime),
+    after: DateTime<Utc>,
+  ) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+    let mut span = (to - from).num_seconds();
+    if span <= 0 {
+      span += Duration::days(1).num_seconds();
+    }
+    // A window past midnight can still be open from the day before
+    let mut day = self.zone.naive_local(after).date().pred_opt();
+    std::iter::from_fn(move || {
+      for _ in 0..MAX_SKIPPED_DAYS {
+        let date = day?;
+        day = date.succ_opt();
+        if self.except.contains(date) || !self.fires_on(date) {
+          continue;
+        }
+        let offset = random::offset(self.seed, date.num_days_from_ce() as i64, span);
+        let event = self
+          .zone
+          .resolve(date.and_time(from) + Duration::seconds(offset));
+        if event > after {
+          return Some(event);
+        }
+      }
+      None
+    })
+  }
+
+  /// Whether the schedule has an event on `date`.
+  fn fires_on(&self, date: NaiveDate) -> bool {
+    let (Some(midnight), Trigger::Schedule(schedule)) = (date.and_hms_opt(0, 0, 0), &self.schedule)
+    else {
+      return false;
+    };
+    self
+      .zone
+      .upcoming(schedule, self.zone.resolve(midnight) - Duration::seconds(1))
+      .next()
+      .is_some_and(|event| self.zone.naive_local(event).date() == date)
+  }
+
+  /// The clock never moves back, so events are not repeated when the system
+  /// time is set back.
+  fn advance(&mut self, now: DateTime<Utc>) {
+    let now = now.with_timezone(&Local);
+    if self.last_tick_local.is_none_or(|last_tick| now > last_tick) {
+      self.last_tick_local = Some(now);
+    }
+  }
+
+  /// The events after `last` up to and including `now`, evaluated in the
+  /// job's time zone and limited by `limit_missed_runs`.
+  fn due(&self, last: DateTime<Utc>, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let limit = match self.limit_missed_runs {
+      0 => usize::MAX,
+      limit => limit,
+    };
+    self
+      .upcoming(last)
+      .take(limit)
+      .take_while(|event| *event <= now)
+      .collect()
+  }
+
+  /// Set the time zone the schedule is evaluated in, defaults to the system's
+  /// time zone.
+  ///
+  /// ```rust,ignore
+  /// let mut job = Job::new("0 0 9 * * * *".parse().unwrap(), "Stand up".to_string());
+  /// job.time_zone("America/New_York".parse().unwrap());
+  /// ```
+  pub fn time_zone(&mut self, zone: Zone) {
+    self.zone = zone;
+  }
+
+  /// Set the level the notification is shown with.
+  pub fn level(&mut self, level: Level) {
+    self.level = level;
+  }
+
+  /// Don't fire on the dates in `calendar`, e.g. public holidays.
+  pub fn except(&mut self, calendar: Calendar) {
+    self.except = calendar;
+  }
+
+  /// Only fire from `starts` until the end of `ends`, in the job's time zone.
+  pub fn dates(&mut self, starts: Option<NaiveDate>, ends: Option<NaiveDate>) {
+    self.starts = starts;
+    self.ends = ends;
+  }
+
+  /// Move each event by up to `minutes` earlier or later.
+  pub fn jitter(&mut self, minutes: u32) {
+    self.jitter = Duration::minutes(minutes as i64);
+  }
+
+  /// Fire once at a random time between `from` and `to` on each day the
+  /// schedule has an event on.
+  pub fn window(&mut self, from: NaiveTime, to: NaiveTime) {
+    self.window = Some((from, to));
+  }
+
+  /// Stop firing after `limit` times.
+  pub fn limit(&mut self, limit: u32) {
+    self.limit = Some(limit);
+  }
+
+  /// Show the notification again until it is acknowledged.
+  pub fn escalation(&mut self, escalation: Escalation) {
+    self.escalation = Some(escalation);
+  }
+
+  /// Set the limit for missed jobs in the case of delayed runs. Setting to 0 means unlimited.
+  ///
+  /// ```rust,ignore
+  /// let mut job = Job::new("0/1 * * * * *".parse().unwrap(), || {
+  ///     println!("I get executed every 1 seconds!");
+  /// });
+  /// job.limit_missed_runs(99);
+  /// ```
+  pub fn limit_missed_runs(&mut self, limit: usize) {
+    self.limit_missed_runs = limit;
+  }
+
+  /// Set last tick to force re-running of missed runs.
+  ///
+  /// ```rust,ignore
+  /// let mut job = Job::new("0/1 * * * * *".parse().unwrap(), || {
+  ///     println!("I get executed every 1 seconds!");
+  /// });
+  /// job.last_tick(Some(Utc::now()));
+  /// ```
+  pub fn last_tick(&mut self, last_tick: Option<DateTime<Utc>>) {
+    self.last_tick = last_tick;
+  }
+}
+
+#[derive(Default)]
+/// The JobScheduler contains and executes the scheduled jobs.
+pub struct JobScheduler {
+  jobs: Vec<Job>,
+  clock: ClockWatch,
+}
+
+impl JobScheduler {
+  /// Create a new `JobScheduler`.
+  pub fn new() -> JobScheduler {
+    JobScheduler {
+      jobs: Vec::new(),
+      clock: ClockWatch::default(),
+    }
+  }
+
+  /// Add a job to the `JobScheduler`
+  ///
+  /// ```rust,ignore
+  /// let mut sched = JobScheduler::new();
+  /// sched.add(Job::new("1/10 * * * * *".parse().unwrap(), || {
+  ///     println!("I get executed every 10 seconds!");
+  /// }));
+  /// ```
+  pub fn add(&mut self, job: Job) -> Uuid {
+    let job_id = job.job_id;
+    self.jobs.push(job);
+
+    job_id
+  }
+
+  /// Remove a job from the `JobScheduler`
+  ///
+  /// ```rust,ignore
+  /// let mut sched = JobScheduler::new();
+  /// let job_id = sched.add(Job::new("1/10 * * * * *".parse().unwrap(), || {
+  ///     println!("I get executed every 10 seconds!");
+  /// }));
+  /// sched.remove(job_id);
+  /// ```
+  pub fn remove(&mut self, job_id: Uuid) -> bool {
+    let mut found_index = None;
+    for (i, job) in self.jobs.iter().enumerate() {
+      if job.job_id == job_id {
+        found_index = Some(i);
+        break;
+      }
+    }
+
+    if let Some(index) = found_index {
+      self.jobs.remove(index);
+    }
+
+    found_index.is_some()
+  }
+
+  /// Remove all jobs from the `JobScheduler`
+  pub fn remove_all(&mut self) {
+    self.jobs.clear();
+  }
+
+  /// Replace the job with the same id, keeping whether it is paused and when
+  /// it was snoozed until. Adds the job if there isn't one.
+  pub fn replace(&mut self, mut job: Job) -> Uuid {
+    if let Some(index) = self.jobs.iter().position(|j| j.job_id == job.job_id) {
+      let old = self.jobs.remove(index);
+      job.paused = old.paused;
+      job.snoozed = old.snoozed;
+      job.repeats = old.repeats;
+      job.shown = old.shown;
+    }
+    self.add(job)
+  }
+
+  /// How many times the job fired on its schedule, `None` if it has no limit.
+  pub fn shown(&self, job_id: Uuid) -> Option<u32> {
+    self
+      .jobs
+      .iter()
+      .find(|job| job.job_id == job_id && job.limit.is_some())
+      .map(|job| job.shown)
+  }
+
+  /// Carries on counting towards the job's limit from `shown`, e.g. after a
+  /// restart.
+  pub fn set_shown(&mut self, job_id: Uuid, shown: u32) -> bool {
+    match self.jobs.iter_mut().find(|job| job.job_id == job_id) {
+      Some(job) => {
+        job.shown = shown;
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// The notification for the job, to show it outside of its schedule.
+  pub fn fire_now(&self, job_id: Uuid) -> Option<Fire> {
+    self
+      .jobs
+      .iter()
+      .find(|job| job.job_id == job_id)
+      .map(|job| job.fire(0))
+  }
+
+  /// Stops repeating an escalating notification until it is shown again.
+  pub fn acknowledge(&mut self, job_id: Uuid) -> bool {
+    match self.jobs.iter_mut().find(|job| job.job_id == job_id) {
+      Some(job) => {
+        job.repeats.clear();
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Pause or resume a job. A paused job doesn't fire, the events it would
+  /// have fired while paused are not shown when it is resumed.
+  pub fn pause(&mut self, job_id: Uuid, paused: bool) -> bool {
+    match self.jobs.iter_mut().find(|job| job.job_id == job_id) {
+      Some(job) => {
+        job.paused = paused;
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Fire a job once more at `at`, in addition to its schedule.
+  pub fn snooze(&mut self, job_id: Uuid, at: DateTime<Utc>) -> bool {
+    match self.jobs.iter_mut().find(|job| job.job_id == job_id) {
+      Some(job) => {
+        job.snoozed.push(at);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Forgets that a snoozed job was going to be shown again at `at`.
+  pub fn cancel_snooze(&mut self, job_id: Uuid, at: DateTime<Utc>) -> bool {
+    match self.jobs.iter_mut().find(|job| job.job_id == job_id) {
+      Some(job) => {
+        job.snoozed.retain(|snoozed| *snoozed != at);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// When each job will fire next, `None` if it is paused or won't fire
+  /// again.
+  pub fn next_runs(&self) -> Vec<(Uuid, Option<DateTime<Utc>>)> {
+    let now = Utc::now();
+    self
+      .jobs
+      .iter()
+      .map(|job| (job.job_id, job.next_run(now)))
+      .collect()
+  }
+
+  /// The `tick` method increments time for the JobScheduler and executes
+  /// any pending jobs. It is recommended to sleep for at least 500
+  /// milliseconds between invocations of this method.
+  ///
+  /// ```rust,ignore
+  /// loop {
+  ///     sched.tick();
+  ///     std::thread::sleep(Duration::from_millis(500));
+  /// }
+  /// ```
+  pub fn tick(&mut self) {
+    for job in &mut self.jobs {
+      job.tick();
+    }
+  }
+
+  /// The `tick_with_system_time` method increments time for the JobScheduler and executes
+  /// any pending jobs. It is recommended to sleep for at least 500
+  /// milliseconds between invocations of this method.
+  ///
+  /// ```rust,ignore
+  /// loop {
+  ///     sched.tick_with_system_time();
+  ///     std::thread::sleep(Duration::from_millis(500));
+  /// }
+  /// ```
+  pub fn tick_with_system_time(&mut self) {
+    for fire in self.tick_due() {
+      if let Err(e) = show_notification(&fire.body, fire.level, || ()) {
+        tracing::error!(label = %fire.body, "Error displaying notification: {}", e);
+      }
+    }
+  }
+
+  /// Increments time for the JobScheduler like `tick_with_system_time` but
+  /// returns the notifications that are due instead of showing them.
+  ///
+  /// If the wall clock jumped forward since the last tick, e.g. because the
+  /// system was suspended, the events that were missed are summarised in a
+  /// single notification instead of being shown one by one. If the clock was
+  /// set back, events that were already shown are not shown again.
+  pub fn tick_due(&mut self) -> Vec<Fire> {
+    let now = Utc::now();
+    match self.clock.observe(now, awake_time()) {
+      ClockJump::Forward(jump) => {
+        tracing::warn!(
+          seconds = jump.num_seconds(),
+          "The clock jumped forward, summarising missed notifications"
+        );
+        let missed: Vec<(Uuid, String, usize)> = self
+          .jobs
+          .iter_mut()
+          .map(|job| (job.job_id, job.label.clone(), job.skip_to(now)))
+          .filter(|(_, _, missed)| *missed > 0)
+          .collect();
+        let job_id = match missed.as_slice() {
+          [(job_id, _, 1)] => Some(*job_id),
+          _ => None,
+        };
+        let missed: Vec<(String, usize)> = missed
+          .into_iter()
+          .map(|(_, label, missed)| (label, missed))
+          .collect();
+        summarise_missed(&missed)
+          .map(|body| Fire {
+            job_id,
+            body,
+            level: Level::Info,
+            repeat: 0,
+          })
+          .into_iter()
+          .collect()
+      }
+      jump => {
+        if let ClockJump::Backward(jump) = jump {
+          tracing::warn!(
+            seconds = jump.num_seconds(),
+            "The clock was set back, notifications that were already shown are skipped"
+          );
+        }
+        let mut fires = Vec::new();
+        for job in &mut self.jobs {
+          let due = job.tick_at(now);
+          if !due.is_empty() {
+            job.escalate(now);
+          }
+          fires.extend(due.iter().map(|_| job.fire(0)));
+          for repeat in job.take_repeats(now) {
+            fires.push(job.fire(repeat));
+          }
+        }
+        fires
+      }
+    }
+  }
+
+  /// The `time_till_next_job` method returns the duration till the next job
+  /// is supposed to run, evaluated in each job's time zone. This can be used
+  /// to sleep until then without waking up at a fixed interval. Returns
+  /// `None` if no job will run again.
+  ///
+  /// ```rust, ignore
+  /// loop {
+  ///     sched.tick_with_system_time();
+  ///     std::thread::sleep(sched.time_till_next_job().unwrap_or(Duration::from_secs(60)));
+  /// }
+  /// ```
+  pub fn time_till_next_job(&self) -> Option<std::time::Duration> {
+    self.time_till_next_job_at(Utc::now())
+  }
+
+  fn time_till_next_job_at(&self, now: DateTime<Utc>) -> Option<std::time::Duration> {
+    self
+      .jobs
+      .iter()
+      .filter_map(|job| job.next_run(now))
+      .min()
+      .map(|next| (next - now).to_std().unwrap_or_default())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn utc(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+  }
+
+  fn job(cron: &str, zone: &str) -> Job {
+    let mut job = Job::new(cron.parse::<Schedule>().unwrap(), "Test".to_string());
+    job.time_zone(zone.parse().unwrap());
+    job.limit_missed_runs(0);
+    job
+  }
+
+  /// Ticks `job` every `step` from `start` to `end` like the scheduler thread
+  /// would and returns everything that fired.
+  fn run(job: &mut Job, start: &str, end: &str, step: Duration) -> Vec<DateTime<Utc>> {
+    let (mut now, end) = (utc(start), utc(end));
+    let mut fired = Vec::new();
+    while now <= end {
+      fired.extend(job.tick_at(now));
+      now += step;
+    }
+    fired
+  }
+
+  #[test]
+  fn test_due_in_named_time_zone() {
+    let job = job("0 0 9 * * * *", "America/New_York");
+    assert_eq!(
+      job.due(utc("2026-07-01T00:00:00Z"), utc("2026-07-02T00:00:00Z")),
+      vec![utc("2026-07-01T13:00:00Z")]
+    );
+    assert!("Mars/Olympus_Mons".parse::<Zone>().is_err());
+    assert_eq!("".parse::<Zone>().unwrap(), Zone::Local);
+  }
+
+  #[test]
+  fn test_time_till_next_job() {
+    let mut scheduler = JobScheduler::new();
+    let now = utc("2026-07-01T12:59:30Z");
+    assert_eq!(scheduler.time_till_next_job_at(now), None);
+    scheduler.add(job("0 0 9 * * * *", "America/New_York"));
+    scheduler.add(job("0 0 * * * * 2020", "UTC"));
+    assert_eq!(
+      scheduler.time_till_next_job_at(now),
+      Some(std::time::Duration::from_secs(30))
+    );
+  }
+
+  #[test]
+  fn test_detects_clock_jumps() {
+    let mut watch = ClockWatch::default();
+    let (wall, awake) = (utc("2026-07-01T12:00:00Z"), awake_time());
+    assert_eq!(watch.observe(wall, awake), ClockJump::None);
+    let (wall, awake) = (
+      wall + Duration::seconds(60),
+      awake + std::time::Duration::from_secs(61),
+    );
+    assert_eq!(watch.observe(wall, awake), ClockJump::None);
+    // Suspended for an hour, the awake time only moved on by a minute.
+    let (wall, awake) = (
+      wall + Duration::hours(1),
+      awake + std::time::Duration::from_secs(60),
+    );
+    assert_eq!(
+      watch.observe(wall, awake),
+      ClockJump::Forward(Duration::minutes(59))
+    );
+    let (wall, awake) = (
+      wall - Duration::hours(1),
+      awake + std::time::Duration::from_secs(60),
+    );
+    assert_eq!(
+      watch.observe(wall, awake),
+      ClockJump::Backward(Duration::minutes(61))
+    );
+  }
+
+  #[test]
+  fn test_clock_set_back_does_not_replay() {
+    let mut job = job("0 0/15 * * * * *", "UTC");
+    assert!(job.tick_at(utc("2026-07-01T12:20:00Z")).is_empty());
+    assert!(job.tick_at(utc("2026-07-01T11:50:00Z")).is_empty());
+    assert!(job.tick_at(utc("2026-07-01T12:10:00Z")).is_empty());
+    assert_eq!(
+      job.tick_at(utc("2026-07-01T12:30:00Z")),
+      vec![utc("2026-07-01T12:30:00Z")]
+    );
+  }
+
+  #[test]
+  fn test_missed_events_are_summarised() {
+    let mut job = job("0 0/15 * * * * *", "UTC");
+    job.tick_at(utc("2026-07-01T12:00:00Z"));
+    assert_eq!(job.skip_to(utc("2026-07-01T13:05:00Z")), 4);
+    assert!(job.tick_at(utc("2026-07-01T13:10:00Z")).is_empty());
+
+    assert_eq!(summarise_missed(&[]), None);
+    assert_eq!(
+      summarise_missed(&[("Stretch".to_string(), 1)]),
+      Some("Stretch".to_string())
+    );
+    assert_eq!(
+      summarise_missed(&[("Stretch".to_string(), 4), ("Drink water".to_string(), 1)]),
+      Some("Missed while away: Stretch (4 times), Drink water".to_string())
+    );
+  }
+
+  #[test]
+  fn test_paused_and_snoozed_jobs() {
+    let mut job = job("0 0 * * * * *", "UTC");
+    job.tick_at(utc("2026-07-01T12:30:00Z"));
+    job.snoozed.push(utc("2026-07-01T12:40:00Z"));
+    assert_eq!(
+      job.next_run(utc("2026-07-01T12:30:00Z")),
+      Some(utc("2026-07-01T12:40:00Z"))
+    );
+    assert_eq!(
+      job.tick_at(utc("2026-07-01T12:45:00Z")),
+      vec![utc("2026-07-01T12:40:00Z")]
+    );
+    job.paused = true;
+    assert_eq!(job.next_run(utc("2026-07-01T12:45:00Z")), None);
+    assert!(job.tick_at(utc("2026-07-01T13:05:00Z")).is_empty());
+    job.paused = false;
+    assert!(job.tick_at(utc("2026-07-01T13:10:00Z")).is_empty());
+    assert_eq!(
+      job.next_run(utc("2026-07-01T13:10:00Z")),
+      Some(utc("2026-07-01T14:00:00Z"))
+    );
+  }
+
+  #[test]
+  fn test_excluded_dates_are_skipped() {
+    // 2026-07-03 is a Friday, so the next run is the Tuesday after the long
+    // weekend
+    let mut job = job("0 0 9 * * Mon-Fri *", "Pacific/Auckland");
+    job.except(Calendar::parse_dates("2026-07-03\n2026-07-06\n").unwrap());
+    job.tick_at(utc("2026-07-02T00:00:00Z"));
+    assert_eq!(
+      job.next_run(utc("2026-07-02T00:00:00Z")),
+      Some(utc("2026-07-06T21:00:00Z"))
+    );
+    assert!(job.tick_at(utc("2026-07-06T20:00:00Z")).is_empty());
+    assert_eq!(
+      job.tick_at(utc("2026-07-06T21:00:00Z")),
+      vec![utc("2026-07-06T21:00:00Z")]
+    );
+  }
+
+  #[test]
+  fn test_start_until_and_limit() {
+    let date = |s: &str| s.parse().ok();
+    let mut job = job("0 0 9 * * * *", "UTC");
+    job.dates(date("2026-07-03"), date("2026-07-10"));
+    job.limit(3);
+    assert_eq!(
+      job.next_run(utc("2026-07-01T00:00:00Z")),
+      Some(utc("2026-07-03T09:00:00Z"))
+    );
+    job.tick_at(utc("2026-07-01T00:00:00Z"));
+    assert!(job.tick_at(utc("2026-07-02T12:00:00Z")).is_empty());
+    assert_eq!(job.tick_at(utc("2026-07-03T12:00:00Z")).len(), 1);
+    job.snoozed.push(utc("2026-07-03T12:10:00Z"));
+    assert_eq!(job.tick_at(utc("2026-07-04T12:00:00Z")).len(), 2);
+    assert_eq!(job.shown, 2, "snoozes don't count towards the limit");
+    assert_eq!(job.tick_at(utc("2026-07-06T12:00:00Z")).len(), 1);
+    assert_eq!(job.next_run(utc("2026-07-06T12:00:00Z")), None);
+
+    job.limit(10);
+    assert_eq!(
+      job.next_run(utc("2026-07-06T12:00:00Z")),
+      Some(utc("2026-07-07T09:00:00Z"))
+    );
+    assert_eq!(job.tick_at(utc("2026-07-20T12:00:00Z")).len(), 4);
+    assert_eq!(job.next_run(utc("2026-07-20T12:00:00Z")), None);
+  }
+
+  #[test]
+  fn test_jitter_and_window() {
+    let mut hourly = job("0 0 * * * * *", "UTC");
+    hourly.jitter(10);
+    let events: Vec<_> = hourly
+      .upcoming(utc("2026-07-01T00:00:00Z"))
+      .take(48)
+      .collect();
+    assert_eq!(
+      events,
+      hourly
+        .upcoming(utc("2026-07-01T00:00:00Z"))
+        .take(48)
+        .collect::<Vec<_>>()
+    );
+    assert!(events.windows(2).all(|pair| pair[0] < pair[1]));
+    let hour = |event: &DateTime<Utc>| event.timestamp() % 3600;
+    assert!(events
+      .iter()
+      .all(|event| hour(event) <= 600 || hour(event) >= 3000));
+    assert!(events.iter().any(|event| hour(event) != 0));
+    // Starting from an event doesn't show it again
+    assert_eq!(hourly.upcoming(events[3]).next(), Some(events[4]));
+
+    let mut job = job("0 0 0 * * Mon-Fri *", "UTC");
+    job.window(
+      NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+      NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+    );
+    // From Friday to Friday
+    let events: Vec<_> = job
+      .upcoming(utc("2026-07-03T00:00:00Z"))
+      .take_while(|event| *event < utc("2026-07-10T00:00:00Z"))
+      .collect();
+    assert_eq!(events.len(), 5);
+    assert!(events.iter().all(|event| {
+      let time = event.time();
+      event.weekday().num_days_from_monday() < 5
+        && time >= NaiveTime::from_hms_opt(10, 0, 0).unwrap()
+        && time < NaiveTime::from_hms_opt(16, 0, 0).unwrap()
+    }));
+    assert_eq!(job.upcoming(events[0]).next(), Some(events[1]));
+  }
+
+  #[test]
+  fn test_escalation_repeats_until_acknowledged() {
+    let mut job = job("0 0 9 * * * *", "UTC");
+    job.escalation(Escalation {
+      every: 5,
+      times: 2,
+      raise_urgency: true,
+    });
+    job.tick_at(utc("2026-07-01T08:00:00Z"));
+    assert_eq!(job.tick_at(utc("2026-07-01T09:00:00Z")).len(), 1);
+    job.escalate(utc("2026-07-01T09:00:00Z"));
+    assert_eq!(
+      job.next_run(utc("2026-07-01T09:00:00Z")),
+      Some(utc("2026-07-01T09:05:00Z"))
+    );
+    assert_eq!(job.take_repeats(utc("2026-07-01T09:06:00Z")), vec![1]);
+    assert_eq!(job.fire(1).level, Level::Warning);
+    assert_eq!(job.fire(2).level, Level::Critical);
+    assert_eq!(job.fire(3).level, Level::Critical);
+
+    let mut scheduler = JobScheduler::new();
+    let job_id = scheduler.add(job);
+    assert!(scheduler.acknowledge(job_id));
+    let job = &mut scheduler.jobs[0];
+    assert!(job.take_repeats(utc("2026-07-01T09:30:00Z")).is_empty());
+  }
+
+  #[test]
+  fn test_repeated_hour_fires_once() {
+    // Clocks go back from 03:00 CEST to 02:00 CET, 02:30 happens twice.
+    let mut job = job("0 30 2 * * * *", "Europe/Berlin");
+    let fired = run(
+      &mut job,
+      "2026-10-24T22:00:00Z",
+      "2026-10-25T04:00:00Z",
+      Duration::seconds(10),
+    );
+    assert_eq!(fired, vec![utc("2026-10-25T00:30:00Z")]);
+  }
+
+  #[test]
+  fn test_events_in_repeated_hour_are_not_replayed() {
+    let mut job = job("0 0/30 * * * * *", "Europe/Berlin");
+    let fired = run(
+      &mut job,
+      "2026-10-24T23:50:00Z",
+      "2026-10-25T02:10:00Z",
+      Duration::minutes(10),
+    );
+    assert_eq!(
+      fired,
+      vec![
+        utc("2026-10-25T00:00:00Z"), // 02:00 CEST
+        utc("2026-10-25T00:30:00Z"), // 02:30 CEST
+        utc("2026-10-25T02:00:00Z"), // 03:00 CET
+      ]
+    );
+  }
+
+  #[test]
+  fn test_skipped_hour_fires_when_clocks_jump() {
+    // Clocks go forward from 02:00 CET to 03:00 CEST, 02:30 never happens.
+    let mut job = job("0 30 2 * * * *", "Europe/Berlin");
+    let fired = run(
+      &mut job,
+      "2026-03-28T22:00:00Z",
+      "2026-03-29T04:00:00Z",
+      Duration::seconds(10),
+    );
+    assert_eq!(fired, vec![utc("2026-03-29T01:00:00Z")]);
+  }
+
+  #[test]
+  fn test_multiple_skipped_events_fire_once() {
+    let mut job = job("0 0/20 * * * * *", "America/New_York");
+    let fired = run(
+      &mut job,
+      "2026-03-08T06:30:00Z",
+      "2026-03-08T07:30:00Z",
+      Duration::minutes(1),
+    );
+    assert_eq!(
+      fired,
+      vec![
+        utc("2026-03-08T06:40:00Z"), // 01:40 EST
+        utc("2026-03-08T07:00:00Z"), // 02:00 - 02:40 skipped, fired at 03:00 EDT
+        utc("2026-03-08T07:20:00Z"), // 03:20 EDT
+      ]
+    );
+  }
+}
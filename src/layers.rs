@@ -0,0 +1,393 @@
+//! Merges the main config file with included files and a `notifier.d`
+//! directory.
+//!
+//! Files are merged from the lowest to the highest precedence:
+//!
+//! 1. The files in the main file's `include` list, in the listed order
+//! 2. The files in the `notifier.d` directory next to the main file, sorted by
+//!    file name
+//! 3. The main file
+//!
+//! Included files are merged before the file including them. A notification
+//! with the same label as one from a lower precedence file replaces it. Labels
+//! that are used more than once in the same file are kept but reported in
+//! [`Notifications::warnings`]. Countdowns are merged the same way by label and
+//! routines by name, the quiet hours of all files apply.
+//!
+//! The `exclude_dates` of a file apply to the notifications in it, the ones of
+//! the main file apply to all notifications.
+
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+};
+
+use crate::{
+  calendar::Calendar,
+  codec::Format,
+  yaml::{load_file_and_deserialise, Sourced, YamlErrors},
+  NotificationDetails, Notifications,
+};
+
+/// The directory next to the main config file whose files are merged in.
+pub const DROP_IN_DIR: &str = "notifier.d";
+
+struct Layer {
+  source: PathBuf,
+  notify: Notifications,
+}
+
+#[derive(Default)]
+struct Layers {
+  layers: Vec<Layer>,
+  warnings: Vec<String>,
+  visited: HashSet<PathBuf>,
+  /// The calendars read so far, `None` if the file couldn't be read
+  calendars: HashMap<PathBuf, Option<Calendar>>,
+}
+
+fn resolve(base: &Path, include: &Path) -> PathBuf {
+  let include = match include.strip_prefix("~") {
+    Ok(rest) => match home::home_dir() {
+      Some(home) => home.join(rest),
+      None => include.to_path_buf(),
+    },
+    Err(_) => include.to_path_buf(),
+  };
+  base.parent().unwrap_or(Path::new(".")).join(include)
+}
+
+/// The files in the `notifier.d` directory next to `path`, sorted by name.
+pub fn drop_in_files(path: &Path) -> Vec<PathBuf> {
+  let dir = path.parent().unwrap_or(Path::new(".")).join(DROP_IN_DIR);
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let mut files: Vec<PathBuf> = entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.is_file())
+    .filter(|path| {
+      let extension = path.extension().and_then(|ext| ext.to_str());
+      extension == Some("yml")
+        || Format::ALL
+          .iter()
+          .any(|format| extension == Some(format.extension()))
+    })
+    .collect();
+  files.sort();
+  files
+}
+
+impl Layers {
+  fn visit(&mut self, path: &Path) -> bool {
+    let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    self.visited.insert(key)
+  }
+
+  fn add_includes(&mut self, path: &Path, includes: &[PathBuf]) {
+    for include in includes {
+      self.add_file(&resolve(path, include));
+    }
+  }
+
+  fn add_file(&mut self, path: &Path) {
+    if !self.visit(path) {
+      self
+        .warnings
+        .push(format!("{} is included more than once", path.display()));
+      return;
+    }
+    match load_file_and_deserialise(&path.to_path_buf()) {
+      Ok(mut notify) => {
+        self.add_includes(path, &notify.include);
+        self.exclude(path, &notify.exclude_dates, &mut notify.notifications);
+        self.layers.push(Layer {
+          source: path.to_path_buf(),
+          notify,
+        });
+      }
+      Err(err) => self
+        .warnings
+        .push(format!("Could not load {}: {}", path.display(), err)),
+    }
+  }
+
+  /// Reads the dates the notifications from the file at `path` are not shown
+  /// on, `global` are the `exclude_dates` of the file.
+  fn exclude(
+    &mut self,
+    path: &Path,
+    global: &[PathBuf],
+    notifications: &mut [NotificationDetails],
+  ) {
+    let global = self.calendar(path, global);
+    for notification in notifications {
+      let mut excluded = global.clone();
+      excluded.extend(&self.calendar(path, &notification.exclude_dates));
+      notification.excluded = excluded;
+    }
+  }
+
+  /// The dates in `files`, relative to the file at `base`.
+  fn calendar(&mut self, base: &Path, files: &[PathBuf]) -> Calendar {
+    let mut calendar = Calendar::default();
+    for file in files {
+      let file = resolve(base, file);
+      let warnings = &mut self.warnings;
+      let loaded =
+        self
+          .calendars
+          .entry(file.clone())
+          .or_insert_with(|| match Calendar::load(&file) {
+            Ok(loaded) => Some(loaded),
+            Err(err) => {
+              warnings.push(format!(
+                "Could not load the dates in {}: {}",
+                file.display(),
+                err
+              ));
+              None
+            }
+          });
+      if let Some(loaded) = loaded {
+        calendar.extend(loaded);
+      }
+    }
+    calendar
+  }
+
+  /// Merges the layers, `main` has the highest precedence.
+  fn merge(self, mut main: Notifications) -> Notifications {
+    let mut warnings = self.warnings;
+    let own = Notifications {
+      quiet_hours: std::mem::take(&mut main.quiet_hours),
+      notifications: std::mem::take(&mut main.notifications),
+      countdowns: std::mem::take(&mut main.countdowns),
+      routines: std::mem::take(&mut main.routines),
+      ..Default::default()
+    };
+    let layers = self
+      .layers
+      .into_iter()
+      .map(|layer| (Some(layer.source), layer.notify));
+    let mut quiet_hours = Vec::new();
+    let mut notifications = Vec::new();
+    let mut countdowns = Vec::new();
+    let mut routines = Vec::new();
+    for (source, notify) in layers.chain(std::iter::once((None, own))) {
+      let name = source.as_ref().map_or_else(
+        || "the config file".to_string(),
+        |s| s.display().to_string(),
+      );
+      quiet_hours.extend(notify.quiet_hours.into_iter().map(|mut hours| {
+        hours.source = source.clone();
+        hours
+      }));
+      let mut merge = Merge {
+        source: &source,
+        name: &name,
+        warnings: &mut warnings,
+      };
+      merge.items(&mut notifications, notify.notifications, |n| &n.label);
+      merge.items(&mut countdowns, notify.countdowns, |c| &c.label);
+      merge.items(&mut routines, notify.routines, |r| &r.name);
+    }
+    Notifications {
+      quiet_hours,
+      notifications,
+      countdowns,
+      routines,
+      warnings,
+      ..main
+    }
+  }
+}
+
+/// Merges the entries of one file into the ones of the files with a lower
+/// precedence.
+struct Merge<'a> {
+  source: &'a Option<PathBuf>,
+  /// The file's name in warnings
+  name: &'a str,
+  warnings: &'a mut Vec<String>,
+}
+
+impl Merge<'_> {
+  /// Adds `items` to `merged`, replacing the entries with the same `key`.
+  fn items<T: Sourced>(&mut self, merged: &mut Vec<T>, items: Vec<T>, key: fn(&T) -> &str) {
+    let mut keys = HashSet::new();
+    for mut item in items {
+      let item_key = key(&item).to_string();
+      if !keys.insert(item_key.clone()) {
+        self.warnings.push(format!(
+          "'{}' is defined more than once in {}",
+          item_key, self.name
+        ));
+      } else if let Some(overridden) = merged
+        .iter()
+        .find(|m| key(m) == item_key && m.source() != self.source.as_ref())
+      {
+        self.warnings.push(format!(
+          "'{}' from {} is overridden by {}",
+          item_key,
+          overridden
+            .source()
+            .map_or_else(String::new, |s| s.display().to_string()),
+          self.name
+        ));
+        merged.retain(|m| key(m) != item_key);
+      }
+      item.set_source(self.source.clone());
+      merged.push(item);
+    }
+  }
+}
+
+/// Loads the config at `path` and merges in its included files and the files
+/// in the `notifier.d` directory next to it.
+pub fn load_config(path: &PathBuf) -> Result<Notifications, YamlErrors> {
+  let mut main = if path.exists() {
+    load_file_and_deserialise(path)?
+  } else {
+    Notifications::default()
+  };
+  let mut layers = Layers::default();
+  layers.visit(path);
+  layers.add_includes(path, &main.include);
+  for file in drop_in_files(path) {
+    layers.add_file(&file);
+  }
+  layers.exclude(path, &[], &mut main.notifications);
+  let global = layers.calendar(path, &main.exclude_dates);
+  let mut merged = layers.merge(main);
+  for notification in merged.notifications.iter_mut() {
+    notification.excluded.extend(&global);
+  }
+  for warning in merged.warnings.iter() {
+    tracing::warn!("{}", warning);
+  }
+  tracing::debug!(
+    path = %path.display(),
+    notifications = merged.notifications.len(),
+    "Loaded the config"
+  );
+  Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+  }
+
+  #[test]
+  fn test_merges_layers_by_precedence() {
+    let dir = std::env::temp_dir().join(format!("notifier-layers-{}", uuid::Uuid::new_v4()));
+    let main = dir.join("notifier.yaml");
+    write(
+      &main,
+      "include: [shared/base.yaml]\nnotifications:\n- label: Stretch\n  cron: '3'\n- label: Mine\n  cron: '4'\n",
+    );
+    write(
+      &dir.join("shared/base.yaml"),
+      "notifications:\n- label: Stretch\n  cron: '1'\n- label: Water\n  cron: '1'\n- label: Water\n  cron: '2'\n",
+    );
+    write(
+      &dir.join("notifier.d/10-team.toml"),
+      "[[notifications]]\nlabel = \"Water\"\ncron = \"2\"\n",
+    );
+    write(&dir.join("notifier.d/ignored.txt"), "not a config");
+
+    let notify = load_config(&main).unwrap();
+    let labels: Vec<(&str, &str)> = notify
+      .notifications
+      .iter()
+      .map(|n| (n.label.as_str(), n.cron.as_str()))
+      .collect();
+    assert_eq!(
+      labels,
+      vec![("Water", "2"), ("Stretch", "3"), ("Mine", "4")]
+    );
+    assert_eq!(
+      notify.notifications[0].source,
+      Some(dir.join("notifier.d/10-team.toml"))
+    );
+    assert_eq!(notify.notifications[1].source, None);
+    assert_eq!(notify.own().notifications.len(), 2);
+    assert_eq!(notify.warnings.len(), 3, "{:?}", notify.warnings);
+    assert!(notify.warnings[0].contains("more than once"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn test_reads_excluded_dates() {
+    let dir = std::env::temp_dir().join(format!("notifier-exclude-{}", uuid::Uuid::new_v4()));
+    let main = dir.join("notifier.yaml");
+    write(
+      &main,
+      "exclude_dates: [company.txt]\nnotifications:\n- label: Mine\n  cron: '1'\n  exclude_dates: [missing.txt]\n",
+    );
+    write(&dir.join("company.txt"), "2026-12-24\n");
+    write(
+      &dir.join("notifier.d/nz.yaml"),
+      "exclude_dates: [holidays/nz.txt]\nnotifications:\n- label: Team\n  cron: '1'\n",
+    );
+    write(
+      &dir.join("notifier.d/holidays/nz.txt"),
+      "2026-12-26 Boxing Day\n",
+    );
+
+    let notify = load_config(&main).unwrap();
+    let date = |s: &str| s.parse().unwrap();
+    let (team, mine) = (&notify.notifications[0], &notify.notifications[1]);
+    assert!(team.excluded.contains(date("2026-12-24")));
+    assert!(team.excluded.contains(date("2026-12-26")));
+    assert!(mine.excluded.contains(date("2026-12-24")));
+    assert!(!mine.excluded.contains(date("2026-12-26")));
+    assert_eq!(notify.warnings.len(), 1, "{:?}", notify.warnings);
+    assert!(notify.warnings[0].contains("missing.txt"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn test_merges_quiet_hours_countdowns_and_routines() {
+    let dir = std::env::temp_dir().join(format!("notifier-sections-{}", uuid::Uuid::new_v4()));
+    let main = dir.join("notifier.yaml");
+    write(
+      &main,
+      "quiet_hours:\n- from: '22:00'\n  to: '07:00'\nnotifications: []\ncountdowns:\n- label: Release\n  date: 2026-12-01\n",
+    );
+    write(
+      &dir.join("notifier.d/team.yaml"),
+      "quiet_hours:\n- from: '12:00'\n  to: '13:00'\nnotifications: []\ncountdowns:\n- label: Release\n  date: 2026-11-01\n- label: Offsite\n  date: 2026-10-01\nroutines:\n- name: Stretch\n  steps:\n  - label: Neck\n    seconds: 30\n",
+    );
+
+    let notify = load_config(&main).unwrap();
+    let team = Some(dir.join("notifier.d/team.yaml"));
+    assert_eq!(notify.quiet_hours.len(), 2);
+    assert_eq!(notify.quiet_hours[0].source, team);
+    let countdowns: Vec<(&str, Option<&PathBuf>)> = notify
+      .countdowns
+      .iter()
+      .map(|c| (c.label.as_str(), c.source.as_ref()))
+      .collect();
+    assert_eq!(
+      countdowns,
+      vec![("Offsite", team.as_ref()), ("Release", None)]
+    );
+    assert_eq!(notify.routines[0].source, team);
+    assert_eq!(notify.warnings.len(), 1, "{:?}", notify.warnings);
+
+    let own = notify.own();
+    assert_eq!(own.quiet_hours.len(), 1);
+    assert_eq!(own.countdowns.len(), 1);
+    assert!(own.routines.is_empty());
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -1,75 +1,201 @@
-mod job_scheduler;
-pub mod notifier_gui;
-mod yaml;
-use std::{path::PathBuf, str::FromStr};
-
-use cron::Schedule;
-use thiserror::Error;
-pub use yaml::{load_file_and_deserialise, save_contents};
-pub use yaml::{NotificationDetails, Notifications};
-
-#[derive(Debug, Error)]
-pub enum Errors {
-  #[error("Could not find file")]
-  CouldNotFindFile,
-  #[error("Error trying to get the home directory path")]
-  CouldNotFindHomeDir,
-  #[error("Error trying to create the config directory path")]
-  CouldNotCreateConfigDir,
-  #[error("Error creating the notification: {0}")]
-  NotificationError(String),
-}
-
-pub fn get_config_path() -> Result<PathBuf, Errors> {
-  match home::home_dir() {
-    Some(home_dir) => {
-      let config_dir = home_dir.join(".config");
-      let config_path = config_dir.join("notifier.yaml");
-      if !config_dir.is_dir() {
-        std::fs::create_dir_all(config_dir).map_err(|_| Errors::CouldNotCreateConfigDir)?;
-      }
-      Ok(config_path)
-    }
-    None => Err(Errors::CouldNotFindHomeDir),
-  }
-}
-
-pub fn check_cron(cron_str: &str) -> bool {
-  let cron = Schedule::from_str(cron_str);
-  let variables = cron_str.split(' ').filter(|x| !x.is_empty()).count();
-  if variables != 7 {
-    println!(
-      "Cron '{}' is invalid: There needs to be 7 variables",
-      cron_str
-    );
-    println!("e.g. {{sec}}   {{min}}   {{hour}}   {{day of month}}   {{month}}   {{day of week}}   {{year}}");
-    println!("See https://crates.io/crates/job_scheduler for more details");
-    return false;
-  }
-  match cron {
-    Ok(_) => true,
-    Err(err) => {
-      println!("Cron {} is invalid: {}", cron_str, err);
-      false
-    }
-  }
-}
-
-#[cfg(test)]
-mod tests {
-  use super::*;
-
-  #[test]
-  fn test_check_cron() {
-    assert_eq!(check_cron("0 0 * * * * *"), true);
-    assert_eq!(check_cron("0 0 * * * *"), false);
-  }
-
-  #[test]
-  fn test_cron_multiple_spaces() {
-    assert_eq!(check_cron("0 0 * * * * * *"), false);
-    assert_eq!(check_cron("0 0 * * * *"), false);
-    assert_eq!(check_cron("0 0 * * * * * "), true);
-    assert_eq!(check_cron("0 0 * * * *  * "), true);
-  }
-}
+mod calendar;
+mod codec;
+pub mod history;
+pub mod job_scheduler;
+mod layers;
+mod logging;
+mod migrations;
+pub mod notifier_gui;
+mod quiet;
+mod random;
+pub mod routine;
+pub mod scheduler;
+mod schema;
+mod state;
+pub mod stats;
+mod yaml;
+mod yaml_edit;
+use std::{
+  path::{Path, PathBuf},
+  str::FromStr,
+};
+
+pub use calendar::Calendar;
+pub use codec::Format;
+use cron::Schedule;
+pub use job_scheduler::Zone;
+pub use layers::{load_config, DROP_IN_DIR};
+pub use logging::{init_logging, LOG_DIR};
+pub use migrations::CURRENT_VERSION;
+pub use schema::{json_schema, json_schema_string};
+pub use state::SchedulerState;
+use thiserror::Error;
+pub use yaml::{
+  convert, deserialise, load_file_and_deserialise, save_contents, save_edit, save_reformatted,
+  ConfigEdit,
+};
+pub use yaml::{
+  Countdown, Escalation, FollowUp, FollowUpWhen, Level, NotificationDetails, Notifications,
+  QuietHours, QuietPolicy, Routine, RoutineStep, TimeWindow,
+};
+
+#[derive(Debug, Error)]
+pub enum Errors {
+  #[error("Could not find file")]
+  CouldNotFindFile,
+  #[error("Error trying to get the home directory path")]
+  CouldNotFindHomeDir,
+  #[error("Error trying to create the config directory path")]
+  CouldNotCreateConfigDir,
+  #[error("Error trying to create the state directory path")]
+  CouldNotCreateStateDir,
+  #[error("Error creating the notification: {0}")]
+  NotificationError(String),
+  #[error("'{0}' is not a valid IANA time zone")]
+  InvalidTimeZone(String),
+}
+
+/// Environment variable with the path of the config file to use.
+pub const CONFIG_ENV: &str = "NOTIFIER_CONFIG";
+
+/// Picks the config file, in order of precedence, from the `--config` flag,
+/// the `NOTIFIER_CONFIG` environment variable, `$XDG_CONFIG_HOME` and
+/// `~/.config`. Paths pointing at a directory use the config file in it.
+fn resolve_config_path(
+  flag: Option<PathBuf>,
+  env_config: Option<PathBuf>,
+  xdg_config_home: Option<PathBuf>,
+  home_dir: Option<PathBuf>,
+) -> Option<PathBuf> {
+  let non_empty = |path: Option<PathBuf>| path.filter(|p| !p.as_os_str().is_empty());
+  if let Some(path) = non_empty(flag).or_else(|| non_empty(env_config)) {
+    return Some(if path.is_dir() {
+      config_file_in(&path)
+    } else {
+      path
+    });
+  }
+  let config_dir = non_empty(xdg_config_home)
+    .filter(|path| path.is_absolute())
+    .or_else(|| home_dir.map(|home| home.join(".config")))?;
+  Some(config_file_in(&config_dir))
+}
+
+/// The path of the config file, see [`resolve_config_path`] for how it is
+/// picked. `flag` is the value of the `--config` command line flag. The
+/// directory of the config file is created if it doesn't exist.
+pub fn get_config_path(flag: Option<PathBuf>) -> Result<PathBuf, Errors> {
+  let path = resolve_config_path(
+    flag,
+    std::env::var_os(CONFIG_ENV).map(PathBuf::from),
+    std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+    home::home_dir(),
+  )
+  .ok_or(Errors::CouldNotFindHomeDir)?;
+  if let Some(config_dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+    if !config_dir.is_dir() {
+      std::fs::create_dir_all(config_dir).map_err(|_| Errors::CouldNotCreateConfigDir)?;
+    }
+  }
+  Ok(path)
+}
+
+/// The directory notifier keeps its state in, `$XDG_STATE_HOME/notifier` or
+/// `~/.local/state/notifier`.
+fn resolve_state_dir(
+  xdg_state_home: Option<PathBuf>,
+  home_dir: Option<PathBuf>,
+) -> Option<PathBuf> {
+  let state_dir = xdg_state_home
+    .filter(|path| path.is_absolute())
+    .or_else(|| home_dir.map(|home| home.join(".local").join("state")))?;
+  Some(state_dir.join("notifier"))
+}
+
+/// The state directory, see [`resolve_state_dir`] for how it is picked. The
+/// directory is created if it doesn't exist.
+pub fn get_state_dir() -> Result<PathBuf, Errors> {
+  let dir = resolve_state_dir(
+    std::env::var_os("XDG_STATE_HOME").map(PathBuf::from),
+    home::home_dir(),
+  )
+  .ok_or(Errors::CouldNotFindHomeDir)?;
+  if !dir.is_dir() {
+    std::fs::create_dir_all(&dir).map_err(|_| Errors::CouldNotCreateStateDir)?;
+  }
+  Ok(dir)
+}
+
+/// The config file in `dir`. The first existing `notifier.yaml`,
+/// `notifier.toml` or `notifier.json` is used, defaulting to `notifier.yaml`.
+pub fn config_file_in(dir: &Path) -> PathBuf {
+  Format::ALL
+    .iter()
+    .map(|format| dir.join(format!("notifier.{}", format.extension())))
+    .find(|path| path.exists())
+    .unwrap_or_else(|| dir.join("notifier.yaml"))
+}
+
+pub fn check_cron(cron_str: &str) -> bool {
+  let cron = Schedule::from_str(cron_str);
+  let variables = cron_str.split(' ').filter(|x| !x.is_empty()).count();
+  if variables != 7 {
+    tracing::warn!(
+      cron = cron_str,
+      "Cron is invalid: There needs to be 7 variables, e.g. {{sec}}   {{min}}   {{hour}}   {{day of month}}   {{month}}   {{day of week}}   {{year}}. See https://crates.io/crates/job_scheduler for more details"
+    );
+    return false;
+  }
+  match cron {
+    Ok(_) => true,
+    Err(err) => {
+      tracing::warn!(cron = cron_str, "Cron is invalid: {}", err);
+      false
+    }
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_config_path_precedence() {
+    let home = Some(PathBuf::from("/home/me"));
+    let xdg = Some(PathBuf::from("/xdg"));
+    let env = Some(PathBuf::from("/env/notifier.toml"));
+    let flag = Some(PathBuf::from("/flag/notifier.json"));
+    assert_eq!(
+      resolve_config_path(flag.clone(), env.clone(), xdg.clone(), home.clone()),
+      flag
+    );
+    assert_eq!(
+      resolve_config_path(None, env.clone(), xdg.clone(), home.clone()),
+      env
+    );
+    assert_eq!(
+      resolve_config_path(Some(PathBuf::new()), None, xdg.clone(), home.clone()),
+      Some(PathBuf::from("/xdg/notifier.yaml"))
+    );
+    assert_eq!(
+      resolve_config_path(None, None, Some(PathBuf::from("relative")), home.clone()),
+      Some(PathBuf::from("/home/me/.config/notifier.yaml"))
+    );
+    assert_eq!(resolve_config_path(None, None, None, None), None);
+  }
+
+  #[test]
+  fn test_check_cron() {
+    assert_eq!(check_cron("0 0 * * * * *"), true);
+    assert_eq!(check_cron("0 0 * * * *"), false);
+  }
+
+  #[test]
+  fn test_cron_multiple_spaces() {
+    assert_eq!(check_cron("0 0 * * * * * *"), false);
+    assert_eq!(check_cron("0 0 * * * *"), false);
+    assert_eq!(check_cron("0 0 * * * * * "), true);
+    assert_eq!(check_cron("0 0 * * * *  * "), true);
+  }
+}
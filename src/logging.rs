@@ -0,0 +1,84 @@
+//! Logging to stderr and to a log file that is rotated daily.
+//!
+//! Release builds on Windows have no console, so the log file is the only
+//! place to find out why a notification wasn't shown.
+
+use std::path::Path;
+
+use tracing::level_filters::LevelFilter;
+use tracing_appender::{
+  non_blocking::WorkerGuard,
+  rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+/// The directory in the state directory the log files are written to.
+pub const LOG_DIR: &str = "logs";
+
+/// How many days of log files are kept.
+const KEEP_LOG_FILES: usize = 7;
+
+/// The filter used when `RUST_LOG` isn't set. `verbose` is the number of
+/// times `--verbose` was given, other crates only log warnings.
+fn default_filter(verbose: u8) -> String {
+  let level = match verbose {
+    0 => LevelFilter::INFO,
+    1 => LevelFilter::DEBUG,
+    _ => LevelFilter::TRACE,
+  };
+  format!("warn,notifier={}", level)
+}
+
+fn filter(verbose: u8) -> EnvFilter {
+  EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_filter(verbose)))
+}
+
+/// Sets up logging to stderr and, if `log_dir` is given, to a log file in it.
+/// The returned guard writes out buffered log lines when it is dropped, so it
+/// needs to be kept until the program exits.
+pub fn init_logging(verbose: u8, log_dir: Option<&Path>) -> Option<WorkerGuard> {
+  let console = fmt::layer()
+    .with_writer(std::io::stderr)
+    .with_filter(filter(verbose));
+  let appender = log_dir.and_then(|dir| {
+    std::fs::create_dir_all(dir)
+      .map_err(|err| eprintln!("Error creating {}: {}", dir.display(), err))
+      .ok()?;
+    RollingFileAppender::builder()
+      .rotation(Rotation::DAILY)
+      .filename_prefix("notifier")
+      .filename_suffix("log")
+      .max_log_files(KEEP_LOG_FILES)
+      .build(dir)
+      .map_err(|err| eprintln!("Error opening the log file in {}: {}", dir.display(), err))
+      .ok()
+  });
+  let (file, guard) = match appender {
+    Some(appender) => {
+      let (writer, guard) = tracing_appender::non_blocking(appender);
+      let layer = fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_filter(filter(verbose));
+      (Some(layer), Some(guard))
+    }
+    None => (None, None),
+  };
+  tracing_subscriber::registry()
+    .with(console)
+    .with(file)
+    .init();
+  guard
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_default_filter() {
+    assert_eq!(default_filter(0), "warn,notifier=info");
+    assert_eq!(default_filter(1), "warn,notifier=debug");
+    assert_eq!(default_filter(4), "warn,notifier=trace");
+  }
+}
@@ -1,90 +1,300 @@
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-extern crate chrono;
-extern crate cron;
-
-use std::path::PathBuf;
-
-use auto_launch::AutoLaunch;
-use eframe::{run_native, NativeOptions};
-use notifier::{load_file_and_deserialise, notifier_gui::Notifier};
-
-struct AppDetails {
-  path: PathBuf,
-  name: String,
-}
-
-fn get_app_name() -> color_eyre::eyre::Result<AppDetails> {
-  let path = std::env::current_exe().unwrap();
-  let name = String::from(path.file_name().unwrap().to_str().unwrap());
-  Ok(AppDetails { path, name })
-}
-
-fn enable_auto_launch() -> color_eyre::eyre::Result<()> {
-  if cfg!(debug_assertions) {
-    return Ok(());
-  }
-  let app_details = get_app_name()?;
-  let auto: AutoLaunch = AutoLaunch::new(
-    app_details.name.as_str(),
-    &app_details.path.as_os_str().to_string_lossy(),
-    &[] as &[&str],
-  );
-  if !auto.is_enabled()? {
-    auto.enable()?;
-  }
-  Ok(())
-}
-
-fn main() -> color_eyre::eyre::Result<()> {
-  let res = enable_auto_launch();
-  color_eyre::install()?;
-  if res.is_err() {
-    return res;
-  }
-  match home::home_dir() {
-    Some(path) => {
-      let config_dir = path.join(".config");
-      if !config_dir.exists() {
-        std::fs::create_dir_all(&config_dir)?;
-      }
-      #[cfg(not(debug_assertions))]
-      let file_path = config_dir.join("notifier.yaml");
-      #[cfg(debug_assertions)]
-      let file_path = std::path::Path::new("notifier.yaml").to_path_buf();
-
-      if file_path.exists() {
-        let notifications = load_file_and_deserialise(&file_path)?;
-        let options = NativeOptions::default();
-        let s = run_native(
-          "Notifier",
-          options,
-          Box::new(|cc| {
-            Ok(Box::new(Notifier::new_with_data(
-              cc,
-              notifications,
-              file_path,
-            )))
-          }),
-        );
-        if let Err(e) = s {
-          eprintln!("Error: {:?}", e);
-        }
-      } else {
-        let options = NativeOptions::default();
-        if !config_dir.exists() {
-          std::fs::create_dir_all(config_dir)?;
-        }
-        let s = run_native(
-          "Notifier",
-          options,
-          Box::new(|cc| Ok(Box::new(Notifier::new(cc, file_path)))),
-        );
-        if let Err(e) = s {
-          eprintln!("Error: {:?}", e);
-        }
-      }
-    }
-    None => println!("Impossible to get your home dir!"),
-  }
-  Ok(())
-}
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+extern crate chrono;
+extern crate cron;
+
+use std::{path::PathBuf, sync::mpsc};
+
+use auto_launch::AutoLaunch;
+use chrono::{DateTime, Duration, Local, Utc};
+use clap::{Parser, Subcommand};
+use eframe::{egui::ViewportCommand, run_native, NativeOptions};
+use notifier::{
+  convert, get_config_path, get_state_dir,
+  history::{parse_time, History, HistoryFilter, Outcome},
+  init_logging, json_schema_string, load_config,
+  notifier_gui::Notifier,
+  scheduler::{Command as SchedulerCommand, Event, Scheduler},
+  stats::{habit_stats, to_csv},
+  Notifications, LOG_DIR,
+};
+
+/// Shows reminders on cron schedules. Without a command the GUI is started.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+  /// The config file to use, defaults to $NOTIFIER_CONFIG or notifier.yaml in $XDG_CONFIG_HOME or ~/.config
+  #[arg(short, long, global = true, value_name = "PATH")]
+  config: Option<PathBuf>,
+  /// Log more details, give it twice to log everything
+  #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+  verbose: u8,
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Print the JSON Schema of the config file
+  Schema {
+    /// Write the schema to this file instead of printing it
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+  },
+  /// Convert a config file between YAML, TOML and JSON based on the file extensions
+  Convert {
+    /// The config file to read
+    input: PathBuf,
+    /// The config file to write, e.g. notifier.toml
+    output: PathBuf,
+  },
+  /// Show the notifications without the GUI until SIGINT or SIGTERM is received
+  Daemon,
+  /// Show when notifications were shown, failed, snoozed, dismissed, done,
+  /// deferred or dropped
+  History {
+    /// Only notifications whose label contains this
+    #[arg(short, long)]
+    label: Option<String>,
+    /// Only entries with this outcome: shown, failed, snoozed, dismissed, done, deferred or dropped
+    #[arg(short, long)]
+    outcome: Option<Outcome>,
+    /// Only entries from this date, e.g. 2026-07-01, or RFC 3339 time onwards
+    #[arg(long, value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+    /// Only entries before this date or RFC 3339 time
+    #[arg(long, value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+    /// Only show the last N entries
+    #[arg(short = 'n', long)]
+    limit: Option<usize>,
+  },
+  /// Run a routine from the config file, showing a notification at each step
+  Routine {
+    /// The name of the routine
+    name: String,
+  },
+  /// Show streaks and how often reminders were marked as done
+  Stats {
+    /// Write the statistics per reminder and day to this CSV file
+    #[arg(long, value_name = "PATH")]
+    csv: Option<PathBuf>,
+  },
+}
+
+struct AppDetails {
+  path: PathBuf,
+  name: String,
+}
+
+fn get_app_name() -> color_eyre::eyre::Result<AppDetails> {
+  let path = std::env::current_exe().unwrap();
+  let name = String::from(path.file_name().unwrap().to_str().unwrap());
+  Ok(AppDetails { path, name })
+}
+
+fn enable_auto_launch() -> color_eyre::eyre::Result<()> {
+  if cfg!(debug_assertions) {
+    return Ok(());
+  }
+  let app_details = get_app_name()?;
+  let auto: AutoLaunch = AutoLaunch::new(
+    app_details.name.as_str(),
+    &app_details.path.as_os_str().to_string_lossy(),
+    &[] as &[&str],
+  );
+  if !auto.is_enabled()? {
+    auto.enable()?;
+  }
+  Ok(())
+}
+
+fn write_schema(output: Option<PathBuf>) -> color_eyre::eyre::Result<()> {
+  let schema = json_schema_string();
+  match output {
+    Some(path) => std::fs::write(path, schema)?,
+    None => println!("{}", schema),
+  }
+  Ok(())
+}
+
+fn main() -> color_eyre::eyre::Result<()> {
+  color_eyre::install()?;
+  let cli = Cli::parse();
+  // Only the GUI and the daemon run long enough to need a log file
+  let log_dir = match cli.command {
+    None | Some(Command::Daemon) => get_state_dir().ok().map(|dir| dir.join(LOG_DIR)),
+    Some(_) => None,
+  };
+  let _guard = init_logging(cli.verbose, log_dir.as_deref());
+  match cli.command {
+    Some(Command::Schema { output }) => write_schema(output),
+    Some(Command::Convert { input, output }) => Ok(convert(&input, &output)?),
+    Some(Command::Daemon) => run_daemon(cli.config),
+    Some(Command::History {
+      label,
+      outcome,
+      since,
+      until,
+      limit,
+    }) => print_history(
+      HistoryFilter {
+        label,
+        outcome,
+        since,
+        until,
+      },
+      limit,
+    ),
+    Some(Command::Stats { csv }) => print_stats(cli.config, csv),
+    Some(Command::Routine { name }) => run_routine(cli.config, &name),
+    None => run_gui(cli.config),
+  }
+}
+
+fn run_gui(config: Option<PathBuf>) -> color_eyre::eyre::Result<()> {
+  enable_auto_launch()?;
+  let file_path = get_config_path(config)?;
+  let notifications = load_config(&file_path)?;
+  let options = NativeOptions::default();
+  let s = run_native(
+    "Notifier",
+    options,
+    Box::new(|cc| {
+      let ctx = cc.egui_ctx.clone();
+      // Close the window like the user would so the scheduler state is saved
+      if let Err(err) = ctrlc::set_handler(move || {
+        ctx.send_viewport_cmd(ViewportCommand::Close);
+        ctx.request_repaint();
+      }) {
+        tracing::error!("Error handling SIGINT and SIGTERM: {}", err);
+      }
+      Ok(Box::new(Notifier::new_with_data(
+        cc,
+        notifications,
+        file_path,
+      )))
+    }),
+  );
+  if let Err(e) = s {
+    tracing::error!("Error running the GUI: {:?}", e);
+  }
+  Ok(())
+}
+
+enum DaemonMessage {
+  Events,
+  Stop,
+}
+
+fn run_daemon(config: Option<PathBuf>) -> color_eyre::eyre::Result<()> {
+  let file_path = get_config_path(config)?;
+  let mut notifications = load_config(&file_path)?;
+  notifications.assign_job_ids();
+  let (tx, rx) = mpsc::channel();
+  let stop = tx.clone();
+  ctrlc::set_handler(move || {
+    let _ = stop.send(DaemonMessage::Stop);
+  })?;
+  let mut scheduler = Scheduler::spawn(&notifications, Some(get_state_dir()?), move || {
+    let _ = tx.send(DaemonMessage::Events);
+  });
+  for message in rx {
+    match message {
+      // The scheduler logs its events, they only need to be drained
+      DaemonMessage::Events => scheduler.events().for_each(drop),
+      DaemonMessage::Stop => {
+        tracing::info!("Stopping");
+        break;
+      }
+    }
+  }
+  scheduler.shutdown();
+  Ok(())
+}
+
+fn run_routine(config: Option<PathBuf>, name: &str) -> color_eyre::eyre::Result<()> {
+  let file_path = get_config_path(config)?;
+  let notifications = load_config(&file_path)?;
+  let Some(routine) = notifications
+    .routines
+    .into_iter()
+    .find(|routine| routine.name == name)
+  else {
+    color_eyre::eyre::bail!(
+      "There is no routine named '{}' in {}",
+      name,
+      file_path.display()
+    );
+  };
+  let (tx, rx) = mpsc::channel();
+  let stop = tx.clone();
+  ctrlc::set_handler(move || {
+    let _ = stop.send(DaemonMessage::Stop);
+  })?;
+  // Without a state directory so the state saved by the GUI or the daemon is
+  // left alone
+  let mut scheduler = Scheduler::spawn(&Notifications::default(), None, move || {
+    let _ = tx.send(DaemonMessage::Events);
+  });
+  scheduler.send(SchedulerCommand::StartRoutine(routine));
+  'run: for message in rx {
+    match message {
+      DaemonMessage::Events => {
+        for event in scheduler.events() {
+          match event {
+            Event::Fired { label, at, .. } => {
+              println!("{} {}", at.with_timezone(&Local).format("%H:%M:%S"), label)
+            }
+            Event::Error(err) => eprintln!("{}", err),
+            Event::RoutineEnded(_) => break 'run,
+            _ => {}
+          }
+        }
+      }
+      DaemonMessage::Stop => break,
+    }
+  }
+  // Waits for the last step to be shown
+  scheduler.shutdown();
+  Ok(())
+}
+
+fn print_history(filter: HistoryFilter, limit: Option<usize>) -> color_eyre::eyre::Result<()> {
+  let history = History::new(get_state_dir()?.join(History::FILE_NAME));
+  let entries: Vec<_> = history
+    .read()?
+    .into_iter()
+    .filter(|entry| filter.matches(entry))
+    .collect();
+  let skip = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+  for entry in entries.iter().skip(skip) {
+    println!("{}", entry);
+  }
+  Ok(())
+}
+
+fn print_stats(config: Option<PathBuf>, csv: Option<PathBuf>) -> color_eyre::eyre::Result<()> {
+  let notifications = load_config(&get_config_path(config)?)?;
+  let labels: Vec<&str> = notifications
+    .notifications
+    .iter()
+    .map(|notification| notification.label.as_str())
+    .collect();
+  let history = History::new(get_state_dir()?.join(History::FILE_NAME));
+  let today = Local::now().date_naive();
+  let stats = habit_stats(&history.read()?, &labels, &Local, today);
+  if let Some(path) = csv {
+    std::fs::write(path, to_csv(&stats))?;
+    return Ok(());
+  }
+  for habit in stats {
+    let compliance = habit
+      .compliance(today - Duration::days(6), today)
+      .map_or("-".to_string(), |c| format!("{:.0}%", c * 100.));
+    println!(
+      "{}: streak {} days (best {}), last 7 days {}",
+      habit.label, habit.current_streak, habit.best_streak, compliance
+    );
+  }
+  Ok(())
+}
@@ -0,0 +1,93 @@
+//! Upgrades older config documents to the current schema.
+//!
+//! Every document has a top level `version` key, files written before it was
+//! introduced are treated as version 1. When a document is loaded the
+//! migrations from its version up to [`CURRENT_VERSION`] are applied to the raw
+//! YAML before it is deserialised.
+//!
+//! GUI edits write entries in the current format into a document without
+//! bumping its version, so migrations need to leave data that is already in the
+//! newer shape unchanged.
+
+use serde_yaml::{Mapping, Value};
+
+/// The schema version written by this build.
+pub const CURRENT_VERSION: u32 = 2;
+
+type Migration = fn(&mut Mapping);
+
+/// `MIGRATIONS[n]` upgrades a document from version `n + 1` to `n + 2`.
+const MIGRATIONS: [Migration; (CURRENT_VERSION - 1) as usize] = [v1_to_v2];
+
+/// Version 2 turned the free form `level` string into a fixed set of levels.
+fn v1_to_v2(doc: &mut Mapping) {
+  let Some(Value::Sequence(notifications)) = doc.get_mut("notifications") else {
+    return;
+  };
+  for notification in notifications.iter_mut().filter_map(Value::as_mapping_mut) {
+    let level = match notification.get("level").and_then(Value::as_str) {
+      Some(level) => match level.trim().to_lowercase().as_str() {
+        "warn" | "warning" => "Warning",
+        "critical" | "error" | "urgent" => "Critical",
+        _ => "Info",
+      },
+      None => "Info",
+    };
+    notification.insert("level".into(), level.into());
+  }
+}
+
+/// The version of the document, documents without one are version 1.
+pub fn version(doc: &Value) -> u32 {
+  doc
+    .get("version")
+    .and_then(Value::as_u64)
+    .map_or(1, |version| version as u32)
+}
+
+/// Applies the migrations needed to bring `doc` up to [`CURRENT_VERSION`].
+///
+/// Documents newer than this build are left as is. Returns the version the
+/// document had before migrating.
+pub fn migrate(doc: &mut Value) -> u32 {
+  let from = version(doc);
+  let Some(mapping) = doc.as_mapping_mut() else {
+    return from;
+  };
+  if from >= CURRENT_VERSION {
+    return from;
+  }
+  for migration in MIGRATIONS.iter().skip(from.saturating_sub(1) as usize) {
+    migration(mapping);
+  }
+  mapping.insert("version".into(), CURRENT_VERSION.into());
+  from
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_migrates_unversioned_document() {
+    let mut doc: Value = serde_yaml::from_str(
+      "notifications:\n- label: A\n  level: info\n- label: B\n  level: WARN\n- label: C\n",
+    )
+    .unwrap();
+    assert_eq!(migrate(&mut doc), 1);
+    let expected: Value = serde_yaml::from_str(
+      "notifications:\n- label: A\n  level: Info\n- label: B\n  level: Warning\n- label: C\n  level: Info\nversion: 2\n",
+    )
+    .unwrap();
+    assert_eq!(doc, expected);
+  }
+
+  #[test]
+  fn test_leaves_current_and_newer_documents() {
+    let original: Value =
+      serde_yaml::from_str("version: 99\nnotifications:\n- label: A\n  level: shout\n").unwrap();
+    let mut doc = original.clone();
+    assert_eq!(migrate(&mut doc), 99);
+    assert_eq!(doc, original);
+  }
+}
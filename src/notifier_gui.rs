@@ -1,310 +1,1925 @@
-use std::{
-  path::PathBuf,
-  str::FromStr,
-  sync::mpsc::{Receiver, Sender},
-  thread,
-};
-
-use chrono::{DateTime, Datelike, Duration, Local, TimeZone};
-
-use cron::Schedule;
-use eframe::{
-  egui::{Button, CentralPanel, Context, RichText, ScrollArea, Slider, Ui, Window},
-  App,
-};
-
-use crate::{
-  check_cron,
-  job_scheduler::{Job, JobScheduler},
-  load_file_and_deserialise, save_contents, NotificationDetails, Notifications,
-};
-
-#[derive(Debug, PartialEq)]
-enum TimeType {
-  Duration,
-  Time,
-}
-
-#[derive(Debug)]
-pub struct Alarm {
-  start_time: DateTime<Local>,
-  duration: chrono::Duration,
-  end_time: DateTime<Local>,
-}
-
-#[derive(Debug, Default)]
-struct AlarmInput {
-  hour: i32,
-  min: i32,
-}
-
-pub struct Notifier {
-  notifications: Notifications,
-  notification_detail: NotificationDetails,
-  selected_index: Option<usize>,
-  path: PathBuf,
-  add_notification: bool,
-  add_alarm: bool,
-  alarm: AlarmInput,
-  alarms: Vec<Alarm>,
-  time_type: TimeType,
-  tx: Sender<()>,
-}
-
-fn thread_and_notifications(rx: Receiver<()>, notifications: Notifications, path: PathBuf) {
-  thread::spawn(move || {
-    let mut schedules = JobScheduler::new();
-    let mut notifications = notifications;
-    loop {
-      if !notifications.notifications.is_empty() {
-        for notify in notifications.notifications.iter_mut() {
-          if notify.job_id.is_none() {
-            let cron = notify.cron.as_str();
-            if check_cron(cron) {
-              let schedule: Schedule = cron.parse().unwrap();
-              let uuid = schedules.add(Job::new(schedule, notify.label.clone()));
-              notify.job_id = Some(uuid);
-            }
-          }
-        }
-        schedules.tick_with_system_time();
-      }
-      if let Ok(_) = rx.try_recv() {
-        schedules.remove_all();
-        if let Ok(n) = load_file_and_deserialise(&path) {
-          notifications = n;
-        }
-      }
-      thread::sleep(std::time::Duration::from_secs(10));
-    }
-  });
-}
-
-impl Notifier {
-  pub fn new(_cc: &eframe::CreationContext<'_>, path: PathBuf) -> Self {
-    let (tx, rx) = std::sync::mpsc::channel::<()>();
-    thread_and_notifications(rx, Notifications::default(), path.clone());
-    Self {
-      path,
-      tx,
-      notifications: Notifications::default(),
-      notification_detail: NotificationDetails::default(),
-      selected_index: None,
-      add_notification: false,
-      time_type: TimeType::Time,
-      alarm: AlarmInput::default(),
-      add_alarm: false,
-      alarms: Vec::new(),
-    }
-  }
-
-  pub fn new_with_data(
-    _cc: &eframe::CreationContext<'_>,
-    notify: Notifications,
-    path: PathBuf,
-  ) -> Self {
-    let (tx, rx) = std::sync::mpsc::channel::<()>();
-    thread_and_notifications(rx, notify.clone(), path.clone());
-    Self {
-      notifications: notify,
-      notification_detail: NotificationDetails::default(),
-      selected_index: None,
-      path,
-      add_notification: false,
-      alarm: AlarmInput::default(),
-      add_alarm: false,
-      time_type: TimeType::Time,
-      alarms: Vec::new(),
-      tx,
-    }
-  }
-
-  fn render_add_notification(&mut self, ctx: &Context) {
-    Window::new("Add a new notification").show(ctx, |ui| {
-      ui.label("Add a new notification configuration");
-      ui.horizontal_top(|ui| {
-        ui.label("Label:");
-        ui.text_edit_singleline(&mut self.notification_detail.label);
-      });
-      ui.horizontal_top(|ui| {
-        ui.label("Cron:");
-        ui.text_edit_singleline(&mut self.notification_detail.cron);
-      });
-      ui.label("e.g. {{sec}}   {{min}}   {{hour}}   {{day of month}}   {{month}}   {{day of week}}   {{year}}");
-      ui.hyperlink_to("Cron details", "https://crates.io/crates/job_scheduler");
-
-      let save_btn = Button::new("Save");
-      let cancel_btn = ui.button("Cancel");
-      if cancel_btn.clicked() {
-        self.notification_detail = NotificationDetails::default();
-              self.add_notification = false;
-              self.selected_index = None;
-      }
-      let cron = Schedule::from_str(self.notification_detail.cron.as_str());
-      let valid = !self.notification_detail.label.is_empty() && cron.is_ok();
-      let save_btn = ui.add_enabled(valid, save_btn);
-      if save_btn.enabled() && save_btn.clicked() {
-        if let Some(index) = self.selected_index {
-          self.notifications.notifications[index].label = self.notification_detail.label.clone();
-          self.notifications.notifications[index].cron = self.notification_detail.cron.clone();
-        } else {
-          self.notification_detail.level = "Info".to_string();
-          self.notifications.notifications.push(self.notification_detail.clone());
-        }
-        let result = save_contents(&self.path, &self.notifications);
-        match result {
-          Ok(()) => {
-            // Some form of a toast or notification for success
-            if let Err(e) = self.tx.send(()) {
-              eprintln!("Error sending message: {:?}", e);
-            }
-            self.notification_detail = NotificationDetails::default();
-              self.add_notification = false;
-              self.selected_index = None;
-            },
-            Err(err) => {
-              // Some form of a toast or notification for failure
-              eprintln!("Error saving to {}", self.path.display());
-              eprintln!("Error saving the notifications: {}", err);
-            }
-        };
-      }
-    });
-  }
-
-  fn render_add_alarm(&mut self, ctx: &Context) {
-    Window::new("Add Alarm").show(ctx, |ui| {
-      ui.label("Add a alarm");
-      ui.add(Slider::new(&mut self.alarm.hour, 0..=23).text("Hour"));
-      ui.add(Slider::new(&mut self.alarm.min, 0..=59).text("Hour"));
-      ui.radio_value(&mut self.time_type, TimeType::Time, "Alarm");
-      ui.radio_value(&mut self.time_type, TimeType::Duration, "Timer");
-      let btn = ui.button("Save");
-      if btn.clicked() {
-        match self.time_type {
-          TimeType::Duration => {
-            let dur =
-              Duration::hours(self.alarm.hour as i64) + Duration::minutes(self.alarm.min as i64);
-            let alarm = Alarm {
-              start_time: Local::now(),
-              duration: dur,
-              end_time: Local::now() + dur,
-            };
-            self.alarms.push(alarm);
-            self.alarm = AlarmInput::default();
-            self.add_alarm = false;
-          }
-          TimeType::Time => {
-            let now = Local::now();
-            let alarm_time = Local
-              .with_ymd_and_hms(
-                now.year(),
-                now.month(),
-                now.day(),
-                self.alarm.hour as u32,
-                self.alarm.min as u32,
-                0,
-              )
-              .unwrap();
-            let dur = alarm_time - now;
-            let alarm = Alarm {
-              start_time: now,
-              duration: dur,
-              end_time: alarm_time,
-            };
-            self.alarms.push(alarm);
-            self.alarm = AlarmInput::default();
-            self.add_alarm = false;
-          }
-        }
-      }
-      let cancel_btn = ui.button("Cancel");
-      if cancel_btn.clicked() {
-        self.alarm = Default::default();
-        self.add_alarm = false;
-      }
-    });
-  }
-
-  fn render_card(&mut self, ui: &mut Ui) {
-    ScrollArea::vertical().show(ui, |ui| {
-      let mut remove = false;
-      let mut edit = false;
-      let mut selected_index = 0;
-      for (index, notification) in self.notifications.notifications.iter().enumerate() {
-        ui.add_space(10.);
-        ui.horizontal_top(|ui| {
-          let label = RichText::new(notification.label.as_str()).size(20.);
-          ui.label(label);
-          let btn = ui.button("Remove");
-          if btn.clicked() {
-            remove = true;
-            selected_index = index;
-          }
-          let btn = ui.button("Edit");
-          if btn.clicked() {
-            edit = true;
-            selected_index = index;
-          }
-        });
-        ui.label(notification.cron.as_str());
-        ui.horizontal_top(|ui| {
-          ui.label("Next notification at: ");
-          let cron = Schedule::from_str(notification.cron.as_str());
-          match cron {
-            Ok(job) => {
-              let mut upcoming = job.upcoming(Local::now().timezone());
-              ui.label(upcoming.next().unwrap_or_else(Local::now).to_string());
-            }
-            Err(err) => {
-              ui.label(format!("Error: {}", err));
-            }
-          }
-        });
-        ui.add_space(10.);
-        ui.separator();
-      }
-      if remove {
-        self.notifications.notifications.remove(selected_index);
-        if let Err(err) = save_contents(&self.path, &self.notifications) {
-          eprintln!("Error: {}", err);
-        } else {
-          if let Err(e) = self.tx.send(()) {
-            eprintln!("Error sending message: {:?}", e);
-          }
-        }
-      }
-      if edit {
-        self.add_notification = true;
-        self.notification_detail = self.notifications.notifications[selected_index].clone();
-        self.selected_index = Some(selected_index);
-      }
-    });
-  }
-}
-
-impl App for Notifier {
-  fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
-    CentralPanel::default().show(ctx, |ui| {
-      if self.notifications.notifications.is_empty() && self.alarms.is_empty() {
-        self.render_add_notification(ctx);
-      } else {
-        self.render_card(ui);
-        let btn = ui.button("Add Notification");
-        if btn.clicked() {
-          self.add_notification = true;
-        }
-        let btn = ui.button("Add Alarm");
-        if btn.clicked() {
-          self.add_alarm = true;
-        }
-        if self.add_notification {
-          self.render_add_notification(ctx);
-        }
-        if self.add_alarm {
-          self.render_add_alarm(ctx);
-        }
-      }
-    });
-  }
-}
+use std::{
+  collections::{HashMap, HashSet},
+  path::PathBuf,
+  time::Instant,
+};
+
+use chrono::{
+  DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
+};
+
+use eframe::{
+  egui::{
+    Align2, Area, CentralPanel, Color32, ComboBox, Context, DragValue, Frame, Grid, Id, Order,
+    ProgressBar, RichText, ScrollArea, Sense, Slider, TextEdit, Ui, Vec2, Window,
+  },
+  App,
+};
+use uuid::Uuid;
+
+use crate::{
+  get_state_dir,
+  history::{History, HistoryEntry, HistoryFilter, Outcome},
+  job_scheduler::{Trigger, Zone},
+  load_config,
+  routine::{format_seconds, Run},
+  save_edit, save_reformatted,
+  scheduler::{self, Command, Event, Scheduler},
+  stats::{habit_stats, to_csv, HabitStats, Period},
+  yaml::YamlErrors,
+  ConfigEdit, Countdown, Escalation, FollowUp, FollowUpWhen, Level, NotificationDetails,
+  Notifications, QuietPolicy, Routine, RoutineStep, SchedulerState, TimeWindow, CURRENT_VERSION,
+};
+
+#[derive(Debug, PartialEq)]
+enum TimeType {
+  Duration,
+  Time,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct Alarm {
+  start_time: DateTime<Local>,
+  duration: chrono::Duration,
+  end_time: DateTime<Local>,
+}
+
+#[derive(Debug, Default)]
+struct AlarmInput {
+  hour: i32,
+  min: i32,
+}
+
+/// The dates as typed in the notification form.
+#[derive(Debug, Default)]
+struct DatesInput {
+  start: String,
+  until: String,
+  /// When a one-off reminder is shown, `None` for a cron schedule
+  at: Option<String>,
+}
+
+impl DatesInput {
+  fn new(notification: &NotificationDetails) -> Self {
+    let text = |date: Option<NaiveDate>| date.map(|date| date.to_string()).unwrap_or_default();
+    Self {
+      start: text(notification.start),
+      until: text(notification.until),
+      at: notification
+        .at
+        .map(|at| at.format(REMINDER_FORMAT).to_string()),
+    }
+  }
+
+  /// Sets the dates of `notification`, an empty date is no date.
+  fn apply(&self, notification: &mut NotificationDetails) -> Result<(), String> {
+    let parse = |text: &str| match text.trim() {
+      "" => Ok(None),
+      date => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| format!("'{}' is not a date like 2026-07-01", date)),
+    };
+    notification.start = parse(&self.start)?;
+    notification.until = parse(&self.until)?;
+    notification.at = match &self.at {
+      Some(at) => Some(
+        NaiveDateTime::parse_from_str(at.trim(), REMINDER_FORMAT)
+          .map_err(|_| format!("'{}' is not a time like 2026-11-03 14:00", at.trim()))?,
+      ),
+      None => None,
+    };
+    Ok(())
+  }
+}
+
+/// How long a toast is shown for, errors are shown for longer.
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(4);
+const ERROR_TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ToastKind {
+  Info,
+  Success,
+  Error,
+}
+
+#[derive(Debug)]
+struct Toast {
+  kind: ToastKind,
+  text: String,
+  shown_at: Instant,
+}
+
+impl Toast {
+  fn duration(&self) -> std::time::Duration {
+    match self.kind {
+      ToastKind::Error => ERROR_TOAST_DURATION,
+      ToastKind::Info | ToastKind::Success => TOAST_DURATION,
+    }
+  }
+}
+
+/// Short messages shown in the bottom right corner of the window that
+/// disappear on their own.
+#[derive(Debug, Default)]
+struct Toasts {
+  toasts: Vec<Toast>,
+}
+
+impl Toasts {
+  fn add(&mut self, kind: ToastKind, text: impl Into<String>) {
+    self.toasts.push(Toast {
+      kind,
+      text: text.into(),
+      shown_at: Instant::now(),
+    });
+  }
+
+  fn info(&mut self, text: impl Into<String>) {
+    self.add(ToastKind::Info, text);
+  }
+
+  fn success(&mut self, text: impl Into<String>) {
+    self.add(ToastKind::Success, text);
+  }
+
+  fn error(&mut self, text: impl Into<String>) {
+    self.add(ToastKind::Error, text);
+  }
+
+  /// Drops the toasts that were shown for long enough by `now` and returns how
+  /// long until the next one should be dropped.
+  fn expire(&mut self, now: Instant) -> Option<std::time::Duration> {
+    self
+      .toasts
+      .retain(|toast| now.duration_since(toast.shown_at) < toast.duration());
+    self
+      .toasts
+      .iter()
+      .map(|toast| toast.duration() - now.duration_since(toast.shown_at))
+      .min()
+  }
+
+  fn show(&mut self, ctx: &Context) {
+    if let Some(next) = self.expire(Instant::now()) {
+      ctx.request_repaint_after(next);
+    }
+    let mut closed = None;
+    Area::new(Id::new("toasts"))
+      .anchor(Align2::RIGHT_BOTTOM, [-10., -10.])
+      .order(Order::Foreground)
+      .show(ctx, |ui| {
+        for (index, toast) in self.toasts.iter().enumerate() {
+          Frame::popup(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+              let color = match toast.kind {
+                ToastKind::Info => ui.visuals().text_color(),
+                ToastKind::Success => Color32::GREEN,
+                ToastKind::Error => Color32::RED,
+              };
+              ui.colored_label(color, &toast.text);
+              if ui.small_button("x").clicked() {
+                closed = Some(index);
+              }
+            });
+          });
+        }
+      });
+    if let Some(index) = closed {
+      self.toasts.remove(index);
+    }
+  }
+}
+
+/// How long the Snooze button delays a notification for.
+const SNOOZE: Duration = Duration::minutes(10);
+
+/// How the time of a one-off reminder is typed and shown.
+const REMINDER_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// A one-off reminder as typed in the "Remind me" box.
+#[derive(Debug)]
+struct ReminderInput {
+  label: String,
+  minutes: i64,
+}
+
+impl Default for ReminderInput {
+  fn default() -> Self {
+    Self {
+      label: String::new(),
+      minutes: 30,
+    }
+  }
+}
+
+/// A countdown as typed in its form.
+#[derive(Debug)]
+struct CountdownInput {
+  label: String,
+  date: String,
+  /// Days before the date separated by commas
+  milestones: String,
+  time: String,
+}
+
+impl Default for CountdownInput {
+  fn default() -> Self {
+    Self {
+      label: String::new(),
+      date: String::new(),
+      milestones: "30, 7, 1, 0".to_string(),
+      time: "09:00".to_string(),
+    }
+  }
+}
+
+impl CountdownInput {
+  fn countdown(&self) -> Result<Countdown, String> {
+    if self.label.trim().is_empty() {
+      return Err("The countdown needs a label".to_string());
+    }
+    let date = NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d")
+      .map_err(|_| format!("'{}' is not a date like 2026-12-25", self.date.trim()))?;
+    let milestones = self
+      .milestones
+      .split(',')
+      .map(str::trim)
+      .filter(|days| !days.is_empty())
+      .map(|days| {
+        days
+          .parse()
+          .map_err(|_| format!("'{}' is not a number of days", days))
+      })
+      .collect::<Result<Vec<i64>, _>>()?;
+    let countdown = Countdown {
+      label: self.label.trim().to_string(),
+      date,
+      milestones,
+      time: (self.time.trim() != "09:00").then(|| self.time.trim().to_string()),
+      level: Level::Info,
+      source: None,
+    };
+    countdown.milestones().map(|_| countdown)
+  }
+}
+
+/// The durations do not disturb can be turned on for, in minutes.
+const DND_MINUTES: [i64; 5] = [30, 60, 120, 240, 480];
+
+pub struct Notifier {
+  notifications: Notifications,
+  notification_detail: NotificationDetails,
+  selected_index: Option<usize>,
+  path: PathBuf,
+  add_notification: bool,
+  add_alarm: bool,
+  alarm: AlarmInput,
+  dates: DatesInput,
+  reminder: ReminderInput,
+  alarms: Vec<Alarm>,
+  time_type: TimeType,
+  scheduler: Scheduler,
+  next_runs: HashMap<Uuid, Option<DateTime<Utc>>>,
+  last_fired: HashMap<Uuid, DateTime<Utc>>,
+  paused: HashSet<Uuid>,
+  toasts: Toasts,
+  history: Option<History>,
+  history_view: HistoryView,
+  done: HashMap<Uuid, DateTime<Utc>>,
+  stats_view: StatsView,
+  quiet_until: Option<DateTime<Utc>>,
+  dnd_until: Option<DateTime<Utc>>,
+  dnd_minutes: i64,
+  /// The routines the scheduler is running
+  running: Vec<Run>,
+  /// The countdown being added, the window is open while it is `Some`
+  countdown: Option<CountdownInput>,
+  /// The routine being added, the window is open while it is `Some`
+  routine_detail: Option<Routine>,
+  /// The notifications that can only be saved by rewriting the whole config
+  /// file, the window asking whether to do that is open while it is `Some`
+  reformat: Option<Notifications>,
+}
+
+/// The name of the file the habit statistics are exported to in the state
+/// directory.
+const STATS_CSV: &str = "habits.csv";
+
+/// How many days or weeks the compliance charts show.
+const CHART_DAYS: i64 = 14;
+const CHART_WEEKS: i64 = 8;
+
+#[derive(Debug, Default)]
+struct StatsView {
+  open: bool,
+  weekly: bool,
+  stats: Vec<HabitStats>,
+}
+
+/// How far back the history window goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HistoryRange {
+  Day,
+  Week,
+  All,
+}
+
+/// The most entries shown in the history window.
+const MAX_HISTORY_ROWS: usize = 500;
+
+#[derive(Debug)]
+struct HistoryView {
+  open: bool,
+  stale: bool,
+  entries: Vec<HistoryEntry>,
+  label: String,
+  outcome: Option<Outcome>,
+  range: HistoryRange,
+}
+
+impl Default for HistoryView {
+  fn default() -> Self {
+    Self {
+      open: false,
+      stale: true,
+      entries: Vec::new(),
+      label: String::new(),
+      outcome: None,
+      range: HistoryRange::Day,
+    }
+  }
+}
+
+impl HistoryView {
+  fn filter(&self) -> HistoryFilter {
+    let since = match self.range {
+      HistoryRange::Day => Some(Utc::now() - Duration::days(1)),
+      HistoryRange::Week => Some(Utc::now() - Duration::weeks(1)),
+      HistoryRange::All => None,
+    };
+    HistoryFilter {
+      label: (!self.label.trim().is_empty()).then(|| self.label.clone()),
+      outcome: self.outcome,
+      since,
+      until: None,
+    }
+  }
+}
+
+impl Notifier {
+  pub fn new(cc: &eframe::CreationContext<'_>, path: PathBuf) -> Self {
+    Self::new_with_data(cc, Notifications::default(), path)
+  }
+
+  pub fn new_with_data(
+    cc: &eframe::CreationContext<'_>,
+    notify: Notifications,
+    path: PathBuf,
+  ) -> Self {
+    let mut notify = notify;
+    notify.assign_job_ids();
+    let state_dir = match get_state_dir() {
+      Ok(dir) => Some(dir),
+      Err(err) => {
+        tracing::warn!("Pausing and snoozing won't be kept after closing: {}", err);
+        None
+      }
+    };
+    let state = state_dir
+      .as_ref()
+      .map(|dir| SchedulerState::load(&dir.join(SchedulerState::FILE_NAME)))
+      .unwrap_or_default();
+    let mut paused = HashSet::new();
+    let mut last_fired = HashMap::new();
+    for notification in notify.notifications.iter() {
+      let Some(job_id) = notification.job_id else {
+        continue;
+      };
+      if state.paused.contains(&notification.state_key()) {
+        paused.insert(job_id);
+      }
+      if let Some(at) = state.last_fired.get(&notification.state_key()) {
+        last_fired.insert(job_id, *at);
+      }
+    }
+    let ctx = cc.egui_ctx.clone();
+    let scheduler = Scheduler::spawn(&notify, state_dir.clone(), move || ctx.request_repaint());
+    Self {
+      notifications: notify,
+      notification_detail: NotificationDetails::default(),
+      selected_index: None,
+      path,
+      add_notification: false,
+      alarm: AlarmInput::default(),
+      dates: DatesInput::default(),
+      reminder: ReminderInput::default(),
+      add_alarm: false,
+      time_type: TimeType::Time,
+      alarms: Vec::new(),
+      scheduler,
+      next_runs: HashMap::new(),
+      last_fired,
+      paused,
+      toasts: Toasts::default(),
+      history: state_dir.map(|dir| History::new(dir.join(History::FILE_NAME))),
+      history_view: HistoryView::default(),
+      done: HashMap::new(),
+      stats_view: StatsView::default(),
+      quiet_until: None,
+      dnd_until: None,
+      dnd_minutes: DND_MINUTES[1],
+      running: Vec::new(),
+      countdown: None,
+      routine_detail: None,
+      reformat: None,
+    }
+  }
+
+  /// Saves the notifications, which already have `edit` applied, to the
+  /// config file. When that would reformat the file the user is asked whether
+  /// to do it anyway.
+  fn save_edit(&mut self, edit: ConfigEdit) -> Result<(), YamlErrors> {
+    let result = save_edit(&self.path, &self.notifications, edit);
+    if let Err(YamlErrors::WouldReformat) = result {
+      self.reformat = Some(self.notifications.clone());
+    }
+    result
+  }
+
+  /// Asks whether to rewrite the whole config file for a change that can't be
+  /// saved in place.
+  fn render_reformat(&mut self, ctx: &Context) {
+    if self.reformat.is_none() {
+      return;
+    }
+    let mut save = false;
+    let mut cancel = false;
+    Window::new("Reformat the config file?").show(ctx, |ui| {
+      ui.label(format!(
+        "The change can't be saved to {} without rewriting the whole file, which loses its comments and formatting.",
+        self.path.display()
+      ));
+      ui.horizontal_top(|ui| {
+        save = ui.button("Save anyway").clicked();
+        cancel = ui.button("Cancel").clicked();
+      });
+    });
+    if cancel {
+      self.reformat = None;
+    }
+    if !save {
+      return;
+    }
+    let Some(notifications) = self.reformat.take() else {
+      return;
+    };
+    match save_reformatted(&self.path, &notifications) {
+      Ok(()) => {
+        self
+          .toasts
+          .success(format!("Saved {}", self.path.display()));
+        self.saved();
+      }
+      Err(err) => {
+        tracing::error!(path = %self.path.display(), "Error saving the notifications: {}", err);
+        self
+          .toasts
+          .error(format!("Error saving the notifications: {}", err));
+      }
+    }
+  }
+
+  /// Reloads the merged config and tells the scheduler what changed.
+  fn saved(&mut self) {
+    match load_config(&self.path) {
+      Ok(mut notifications) => {
+        for command in scheduler::sync(
+          &self.notifications.notifications,
+          &mut notifications.notifications,
+        ) {
+          if let Command::Remove(job_id) = command {
+            self.next_runs.remove(&job_id);
+            self.last_fired.remove(&job_id);
+            self.paused.remove(&job_id);
+          }
+          self.scheduler.send(command);
+        }
+        if notifications.countdowns != self.notifications.countdowns {
+          self
+            .scheduler
+            .send(Command::Countdowns(notifications.countdowns.clone()));
+        }
+        if notifications.quiet_hours != self.notifications.quiet_hours {
+          self
+            .scheduler
+            .send(Command::QuietHours(notifications.quiet_hours.clone()));
+        }
+        self.notifications = notifications;
+      }
+      Err(err) => {
+        tracing::error!(path = %self.path.display(), "Error reloading the config: {}", err);
+        self
+          .toasts
+          .error(format!("Error reloading {}: {}", self.path.display(), err));
+      }
+    }
+  }
+
+  /// Applies the events sent by the scheduler thread since the last frame.
+  fn receive_events(&mut self) {
+    for event in self.scheduler.events() {
+      match event {
+        Event::Fired { job_id, label, at } => {
+          self.history_view.stale = true;
+          if let Some(job_id) = job_id {
+            self.last_fired.insert(job_id, at);
+          }
+          self.toasts.info(label);
+        }
+        Event::Error(err) => self.toasts.error(err),
+        Event::NextRunChanged { job_id, next } => {
+          self.next_runs.insert(job_id, next);
+        }
+        Event::QuietChanged { until, dnd_until } => {
+          self.quiet_until = until;
+          self.dnd_until = dnd_until.filter(|dnd_until| *dnd_until > Utc::now());
+        }
+        Event::RoutineStarted(run) => {
+          self
+            .running
+            .retain(|running| running.routine.name != run.routine.name);
+          self.running.push(run);
+        }
+        Event::RoutineEnded(name) => self.running.retain(|run| run.routine.name != name),
+      }
+    }
+  }
+
+  fn render_add_notification(&mut self, ctx: &Context) {
+    let labels: Vec<String> = self
+      .notifications
+      .notifications
+      .iter()
+      .map(|notification| notification.label.clone())
+      .filter(|label| *label != self.notification_detail.label)
+      .collect();
+    Window::new("Add a new notification").show(ctx, |ui| {
+      ui.label("Add a new notification configuration");
+      ui.horizontal_top(|ui| {
+        ui.label("Label:");
+        ui.text_edit_singleline(&mut self.notification_detail.label);
+      });
+      let mut once = self.dates.at.is_some();
+      let mut follows = self.notification_detail.follow_up.is_some();
+      ui.horizontal_top(|ui| {
+        if ui
+          .checkbox(&mut once, "Only once")
+          .on_hover_text("Show the notification once at a date and time instead of on a cron schedule")
+          .changed()
+        {
+          follows &= !once;
+        }
+        if ui
+          .checkbox(&mut follows, "Follow up")
+          .on_hover_text("Show the notification some minutes after another notification")
+          .changed()
+        {
+          once &= !follows;
+        }
+      });
+      match (follows, &mut self.notification_detail.follow_up) {
+        (true, Some(follow_up)) => {
+          ui.horizontal_top(|ui| {
+            ui.add(DragValue::new(&mut follow_up.minutes).range(1..=1440).suffix(" min"));
+            ui.label("after");
+            ComboBox::from_id_salt("follow_up_after")
+              .selected_text(follow_up.after.as_str())
+              .show_ui(ui, |ui| {
+                for label in &labels {
+                  ui.selectable_value(&mut follow_up.after, label.clone(), label);
+                }
+              });
+            ComboBox::from_id_salt("follow_up_when")
+              .selected_text(follow_up.when.to_string())
+              .show_ui(ui, |ui| {
+                for when in [FollowUpWhen::Shown, FollowUpWhen::Done, FollowUpWhen::NotDone] {
+                  ui.selectable_value(&mut follow_up.when, when, when.to_string());
+                }
+              });
+          });
+        }
+        (true, follow_up @ None) => {
+          *follow_up = Some(FollowUp {
+            after: labels.first().cloned().unwrap_or_default(),
+            minutes: 20,
+            when: FollowUpWhen::default(),
+          })
+        }
+        (false, follow_up) => *follow_up = None,
+      }
+      match (once, &mut self.dates.at) {
+        (true, Some(at)) => {
+          ui.horizontal_top(|ui| {
+            ui.label("At:");
+            ui.add(TextEdit::singleline(at).hint_text("2026-11-03 14:00"));
+          });
+        }
+        (true, at @ None) => *at = Some((Local::now() + Duration::hours(1)).format(REMINDER_FORMAT).to_string()),
+        (false, at) if follows => *at = None,
+        (false, at) => {
+          *at = None;
+          ui.horizontal_top(|ui| {
+            ui.label("Cron:");
+            ui.text_edit_singleline(&mut self.notification_detail.cron);
+          });
+          ui.label("e.g. {{sec}}   {{min}}   {{hour}}   {{day of month}}   {{month}}   {{day of week}}   {{year}}");
+          ui.hyperlink_to("Cron details", "https://crates.io/crates/job_scheduler");
+        }
+      }
+      ui.horizontal_top(|ui| {
+        ui.label("Time zone:");
+        let mut timezone = self.notification_detail.timezone.clone().unwrap_or_default();
+        ui.text_edit_singleline(&mut timezone)
+          .on_hover_text("An IANA time zone such as America/New_York, leave empty for the system's time zone");
+        self.notification_detail.timezone = (!timezone.trim().is_empty()).then(|| timezone.trim().to_string());
+      });
+      ui.horizontal_top(|ui| {
+        ui.label("Skip dates in:");
+        let mut files = self
+          .notification_detail
+          .exclude_dates
+          .iter()
+          .map(|file| file.display().to_string())
+          .collect::<Vec<_>>()
+          .join(", ");
+        ui.text_edit_singleline(&mut files)
+          .on_hover_text("Files of dates or .ics calendars, separated by commas, relative to the config file");
+        // Empty files are kept while typing and dropped when saving
+        self.notification_detail.exclude_dates = match files.trim() {
+          "" => Vec::new(),
+          files => files.split(',').map(|file| PathBuf::from(file.trim())).collect(),
+        };
+      });
+      ui.horizontal_top(|ui| {
+        ui.label("From:");
+        ui.add(TextEdit::singleline(&mut self.dates.start).hint_text("2026-07-01").desired_width(90.));
+        ui.label("until:");
+        ui.add(TextEdit::singleline(&mut self.dates.until).hint_text("2026-07-14").desired_width(90.));
+      })
+      .response
+      .on_hover_text("Only show the notification between these dates, leave empty to show it from now on and forever");
+      let mut limited = self.notification_detail.max_occurrences.is_some();
+      ui.horizontal_top(|ui| {
+        ui.checkbox(&mut limited, "Stop after");
+        match (limited, &mut self.notification_detail.max_occurrences) {
+          (true, Some(limit)) => {
+            ui.add(DragValue::new(limit).range(1..=10000).suffix(" times"));
+          }
+          (true, limit @ None) => *limit = Some(10),
+          (false, limit) => *limit = None,
+        }
+      });
+      let mut jittered = self.notification_detail.jitter.is_some();
+      ui.horizontal_top(|ui| {
+        ui.checkbox(&mut jittered, "Vary by up to")
+          .on_hover_text("Show the notification a little earlier or later each time");
+        match (jittered, &mut self.notification_detail.jitter) {
+          (true, Some(jitter)) => {
+            ui.add(DragValue::new(jitter).range(1..=720).suffix(" min"));
+          }
+          (true, jitter @ None) => *jitter = Some(5),
+          (false, jitter) => *jitter = None,
+        }
+      });
+      let mut random = self.notification_detail.within.is_some();
+      ui.horizontal_top(|ui| {
+        ui.checkbox(&mut random, "Once a day between")
+          .on_hover_text("Show the notification at a random time in the window on each day the cron matches, every day if the cron is empty");
+        match (random, &mut self.notification_detail.within) {
+          (true, Some(within)) => {
+            ui.add(TextEdit::singleline(&mut within.from).desired_width(50.));
+            ui.label("and");
+            ui.add(TextEdit::singleline(&mut within.to).desired_width(50.));
+          }
+          (true, within @ None) => {
+            *within = Some(TimeWindow {
+              from: "10:00".to_string(),
+              to: "16:00".to_string(),
+            })
+          }
+          (false, within) => *within = None,
+        }
+      });
+      ComboBox::from_label("Level")
+        .selected_text(self.notification_detail.level.to_string())
+        .show_ui(ui, |ui| {
+          for level in [Level::Info, Level::Warning, Level::Critical] {
+            ui.selectable_value(&mut self.notification_detail.level, level, level.to_string());
+          }
+        });
+      let mut escalate = self.notification_detail.escalate.is_some();
+      ui.checkbox(&mut escalate, "Repeat until done")
+        .on_hover_text("Show the notification again until Done is clicked");
+      match (escalate, &mut self.notification_detail.escalate) {
+        (true, Some(escalation)) => {
+          ui.horizontal_top(|ui| {
+            ui.label("Every");
+            ui.add(DragValue::new(&mut escalation.every).range(1..=1440).suffix(" min"));
+            ui.label("up to");
+            ui.add(DragValue::new(&mut escalation.times).range(1..=100).suffix(" times"));
+          });
+          ui.checkbox(&mut escalation.raise_urgency, "Raise the level each time");
+        }
+        (true, escalation @ None) => *escalation = Some(Escalation::default()),
+        (false, escalation) => *escalation = None,
+      }
+      let policy_text = |quiet: Option<QuietPolicy>| match quiet {
+        Some(policy) => policy.to_string(),
+        None => "Default".to_string(),
+      };
+      ComboBox::from_label("During quiet hours")
+        .selected_text(policy_text(self.notification_detail.quiet))
+        .show_ui(ui, |ui| {
+          for quiet in [None, Some(QuietPolicy::Drop), Some(QuietPolicy::Defer), Some(QuietPolicy::Bypass)] {
+            ui.selectable_value(&mut self.notification_detail.quiet, quiet, policy_text(quiet));
+          }
+        })
+        .response
+        .on_hover_text("By default critical notifications are shown anyway and others are shown once the quiet time is over");
+
+      let save_btn = ui.button("Save");
+      let cancel_btn = ui.button("Cancel");
+      if cancel_btn.clicked() {
+        self.notification_detail = NotificationDetails::default();
+        self.dates = DatesInput::default();
+              self.add_notification = false;
+              self.selected_index = None;
+      }
+      if save_btn.clicked() {
+        self
+          .notification_detail
+          .exclude_dates
+          .retain(|file| !file.as_os_str().is_empty());
+      }
+      let invalid = self
+        .dates
+        .apply(&mut self.notification_detail)
+        .err()
+        .or_else(|| {
+          let others: Vec<&NotificationDetails> = self
+            .notifications
+            .notifications
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != self.selected_index)
+            .map(|(_, notification)| notification)
+            .collect();
+          validation_error(&self.notification_detail, &others)
+        });
+      if let (true, Some(invalid)) = (save_btn.clicked(), &invalid) {
+        self.toasts.error(invalid.as_str());
+      }
+      if save_btn.clicked() && invalid.is_none() {
+        let (edit, previous) = if let Some(index) = self.selected_index {
+          let previous = std::mem::replace(
+            &mut self.notifications.notifications[index],
+            self.notification_detail.clone(),
+          );
+          (ConfigEdit::Update(index), Some((index, previous)))
+        } else {
+          self.notifications.notifications.push(self.notification_detail.clone());
+          (ConfigEdit::Add, None)
+        };
+        let result = self.save_edit(edit);
+        match result {
+          Ok(()) => {
+            self.toasts.success(format!("Saved '{}'", self.notification_detail.label));
+            self.saved();
+            self.notification_detail = NotificationDetails::default();
+            self.dates = DatesInput::default();
+              self.add_notification = false;
+              self.selected_index = None;
+            },
+            Err(err) => {
+              match previous {
+                Some((index, previous)) => self.notifications.notifications[index] = previous,
+                None => {
+                  self.notifications.notifications.pop();
+                }
+              }
+              tracing::error!(path = %self.path.display(), "Error saving the notifications: {}", err);
+              self.toasts.error(format!("Error saving the notifications: {}", err));
+            }
+        };
+      }
+    });
+  }
+
+  fn render_add_alarm(&mut self, ctx: &Context) {
+    Window::new("Add Alarm").show(ctx, |ui| {
+      ui.label("Add a alarm");
+      ui.add(Slider::new(&mut self.alarm.hour, 0..=23).text("Hour"));
+      ui.add(Slider::new(&mut self.alarm.min, 0..=59).text("Hour"));
+      ui.radio_value(&mut self.time_type, TimeType::Time, "Alarm");
+      ui.radio_value(&mut self.time_type, TimeType::Duration, "Timer");
+      let btn = ui.button("Save");
+      if btn.clicked() {
+        match self.time_type {
+          TimeType::Duration => {
+            let dur =
+              Duration::hours(self.alarm.hour as i64) + Duration::minutes(self.alarm.min as i64);
+            let alarm = Alarm {
+              start_time: Local::now(),
+              duration: dur,
+              end_time: Local::now() + dur,
+            };
+            self.alarms.push(alarm);
+            self.alarm = AlarmInput::default();
+            self.add_alarm = false;
+          }
+          TimeType::Time => {
+            let now = Local::now();
+            let alarm_time = Local
+              .with_ymd_and_hms(
+                now.year(),
+                now.month(),
+                now.day(),
+                self.alarm.hour as u32,
+                self.alarm.min as u32,
+                0,
+              )
+              .unwrap();
+            let dur = alarm_time - now;
+            let alarm = Alarm {
+              start_time: now,
+              duration: dur,
+              end_time: alarm_time,
+            };
+            self.alarms.push(alarm);
+            self.alarm = AlarmInput::default();
+            self.add_alarm = false;
+          }
+        }
+      }
+      let cancel_btn = ui.button("Cancel");
+      if cancel_btn.clicked() {
+        self.alarm = Default::default();
+        self.add_alarm = false;
+      }
+    });
+  }
+
+  fn render_history(&mut self, ctx: &Context) {
+    let Some(history) = &self.history else {
+      self.history_view.open = false;
+      self
+        .toasts
+        .error("The history isn't available without a state directory");
+      return;
+    };
+    let view = &mut self.history_view;
+    if view.stale {
+      view.stale = false;
+      match history.read() {
+        Ok(entries) => view.entries = entries,
+        Err(err) => self
+          .toasts
+          .error(format!("Error reading the history: {}", err)),
+      }
+    }
+    let mut open = view.open;
+    Window::new("History").open(&mut open).show(ctx, |ui| {
+      ui.horizontal_top(|ui| {
+        ui.label("Label:");
+        ui.text_edit_singleline(&mut view.label);
+        let outcome = view.outcome.map_or("Any".to_string(), |o| o.to_string());
+        ComboBox::from_label("Outcome")
+          .selected_text(outcome)
+          .show_ui(ui, |ui| {
+            ui.selectable_value(&mut view.outcome, None, "Any");
+            for outcome in Outcome::ALL {
+              ui.selectable_value(&mut view.outcome, Some(outcome), outcome.to_string());
+            }
+          });
+      });
+      ui.horizontal_top(|ui| {
+        ui.radio_value(&mut view.range, HistoryRange::Day, "Last 24 hours");
+        ui.radio_value(&mut view.range, HistoryRange::Week, "Last 7 days");
+        ui.radio_value(&mut view.range, HistoryRange::All, "All");
+        if ui.button("Refresh").clicked() {
+          view.stale = true;
+        }
+      });
+      ui.separator();
+      let filter = view.filter();
+      let entries: Vec<&HistoryEntry> = view
+        .entries
+        .iter()
+        .rev()
+        .filter(|entry| filter.matches(entry))
+        .take(MAX_HISTORY_ROWS)
+        .collect();
+      if entries.is_empty() {
+        ui.label("Nothing happened in this time");
+        return;
+      }
+      ScrollArea::vertical().show(ui, |ui| {
+        Grid::new("history").striped(true).show(ui, |ui| {
+          for entry in entries {
+            ui.label(Zone::Local.format(entry.at));
+            ui.label(entry.outcome.to_string());
+            ui.label(entry.label.as_str());
+            ui.label(entry.detail.as_deref().unwrap_or_default());
+            ui.end_row();
+          }
+        });
+      });
+    });
+    view.open = open;
+  }
+
+  fn refresh_stats(&mut self) {
+    let Some(history) = &self.history else {
+      return;
+    };
+    let labels: Vec<&str> = self
+      .notifications
+      .notifications
+      .iter()
+      .map(|notification| notification.label.as_str())
+      .collect();
+    match history.read() {
+      Ok(entries) => {
+        self.stats_view.stats = habit_stats(&entries, &labels, &Local, Local::now().date_naive())
+      }
+      Err(err) => self
+        .toasts
+        .error(format!("Error reading the history: {}", err)),
+    }
+  }
+
+  fn export_stats(&mut self) {
+    let Some(dir) = get_state_dir().ok() else {
+      self.toasts.error("The state directory isn't available");
+      return;
+    };
+    let path = dir.join(STATS_CSV);
+    match std::fs::write(&path, to_csv(&self.stats_view.stats)) {
+      Ok(()) => self
+        .toasts
+        .success(format!("Exported to {}", path.display())),
+      Err(err) => {
+        tracing::error!(path = %path.display(), "Error exporting the stats: {}", err);
+        self
+          .toasts
+          .error(format!("Error exporting the stats: {}", err));
+      }
+    }
+  }
+
+  fn render_stats(&mut self, ctx: &Context) {
+    let mut open = self.stats_view.open;
+    let (mut refresh, mut export) = (false, false);
+    Window::new("Stats").open(&mut open).show(ctx, |ui| {
+      let view = &mut self.stats_view;
+      ui.horizontal_top(|ui| {
+        ui.radio_value(&mut view.weekly, false, "Daily");
+        ui.radio_value(&mut view.weekly, true, "Weekly");
+        refresh = ui.button("Refresh").clicked();
+        export = ui
+          .button("Export CSV")
+          .on_hover_text(format!("Writes {} to the state directory", STATS_CSV))
+          .clicked();
+      });
+      ui.separator();
+      if view.stats.is_empty() {
+        ui.label("Mark reminders as done to see your progress here");
+        return;
+      }
+      let today = Local::now().date_naive();
+      let week_ago = today - Duration::days(6);
+      ScrollArea::vertical().show(ui, |ui| {
+        for habit in view.stats.iter() {
+          ui.label(RichText::new(habit.label.as_str()).size(16.));
+          let compliance = habit
+            .compliance(week_ago, today)
+            .map_or("-".to_string(), |c| format!("{:.0}%", c * 100.));
+          ui.label(format!(
+            "Streak: {} days (best {}), last 7 days: {}",
+            habit.current_streak, habit.best_streak, compliance
+          ));
+          let periods = if view.weekly {
+            habit.weekly(today - Duration::weeks(CHART_WEEKS - 1), today)
+          } else {
+            habit.daily(today - Duration::days(CHART_DAYS - 1), today)
+          };
+          compliance_chart(ui, &periods);
+          ui.add_space(10.);
+        }
+      });
+    });
+    self.stats_view.open = open;
+    if refresh {
+      self.refresh_stats();
+    }
+    if export {
+      self.export_stats();
+    }
+  }
+
+  /// The days left until each countdown's date, or since it.
+  fn render_countdowns(&mut self, ui: &mut Ui) {
+    let today = Local::now().date_naive();
+    let mut remove = None;
+    for (index, countdown) in self.notifications.countdowns.iter().enumerate() {
+      let days = countdown.days_left(today);
+      ui.add_space(10.);
+      ui.horizontal_top(|ui| {
+        ui.label(RichText::new(countdown.label.as_str()).size(20.));
+        if countdown.source.is_none() && ui.button("Remove").clicked() {
+          remove = Some(index);
+        }
+      });
+      if let Some(source) = &countdown.source {
+        ui.label(format!("From {}", source.display()));
+      }
+      let left = match days {
+        1 => "1 day to go".to_string(),
+        2.. => format!("{} days to go", days),
+        0 => "Today".to_string(),
+        -1 => "1 day ago".to_string(),
+        _ => format!("{} days ago", -days),
+      };
+      ui.label(RichText::new(left).size(16.).strong());
+      ui.label(countdown.date.format("%A %-d %B %Y").to_string());
+      match countdown.next_milestone(today) {
+        Some(milestone) => ui.label(format!(
+          "Next reminder on {}",
+          (countdown.date - Duration::days(milestone)).format("%Y-%m-%d")
+        )),
+        None => ui.label("No more reminders"),
+      };
+    }
+    if let Some(index) = remove {
+      let removed = self.notifications.countdowns.remove(index);
+      match self.save_edit(ConfigEdit::RemoveCountdown(index)) {
+        Ok(()) => {
+          self.toasts.success(format!("Removed '{}'", removed.label));
+          self.saved();
+        }
+        Err(err) => {
+          tracing::error!(path = %self.path.display(), "Error removing the countdown: {}", err);
+          self
+            .toasts
+            .error(format!("Error removing '{}': {}", removed.label, err));
+          self.notifications.countdowns.insert(index, removed);
+        }
+      }
+    }
+  }
+
+  fn render_add_countdown(&mut self, ctx: &Context) {
+    let Some(input) = &mut self.countdown else {
+      return;
+    };
+    let mut save = false;
+    let mut cancel = false;
+    Window::new("Add a countdown").show(ctx, |ui| {
+      ui.horizontal_top(|ui| {
+        ui.label("Label:");
+        ui.text_edit_singleline(&mut input.label);
+      });
+      ui.horizontal_top(|ui| {
+        ui.label("Date:");
+        ui.add(TextEdit::singleline(&mut input.date).hint_text("2026-12-25"));
+      });
+      ui.horizontal_top(|ui| {
+        ui.label("Remind me");
+        ui.text_edit_singleline(&mut input.milestones)
+          .on_hover_text("Days before the date separated by commas, 0 for the day itself and negative numbers for days after it");
+        ui.label("days before at");
+        ui.add(TextEdit::singleline(&mut input.time).desired_width(50.));
+      });
+      let invalid = input.countdown().err();
+      if let Some(invalid) = &invalid {
+        ui.colored_label(Color32::RED, invalid);
+      }
+      ui.horizontal_top(|ui| {
+        save = ui
+          .add_enabled(invalid.is_none(), eframe::egui::Button::new("Save"))
+          .clicked();
+        cancel = ui.button("Cancel").clicked();
+      });
+    });
+    if cancel {
+      self.countdown = None;
+    }
+    if !save {
+      return;
+    }
+    let Some(Ok(countdown)) = self.countdown.as_ref().map(CountdownInput::countdown) else {
+      return;
+    };
+    self.countdown = None;
+    let label = countdown.label.clone();
+    self.notifications.countdowns.push(countdown);
+    match self.save_edit(ConfigEdit::AddCountdown) {
+      Ok(()) => {
+        self.toasts.success(format!("Saved '{}'", label));
+        self.saved();
+      }
+      Err(err) => {
+        self.notifications.countdowns.pop();
+        tracing::error!(path = %self.path.display(), "Error saving the countdown: {}", err);
+        self
+          .toasts
+          .error(format!("Error saving the countdown: {}", err));
+      }
+    }
+  }
+
+  /// The routines with buttons to start and stop them and the progress of the
+  /// running ones.
+  fn render_routines(&mut self, ui: &mut Ui) {
+    let now = Utc::now();
+    let mut remove = None;
+    for (index, routine) in self.notifications.routines.iter().enumerate() {
+      let run = self
+        .running
+        .iter()
+        .find(|run| run.routine.name == routine.name);
+      ui.add_space(10.);
+      ui.horizontal_top(|ui| {
+        ui.label(RichText::new(routine.name.as_str()).size(20.));
+        let total = routine.steps.iter().map(|step| step.seconds).sum();
+        ui.label(format!(
+          "{} steps, {}",
+          routine.steps.len(),
+          format_seconds(total)
+        ));
+        match run {
+          Some(_) if ui.button("Stop").clicked() => self
+            .scheduler
+            .send(Command::StopRoutine(routine.name.clone())),
+          None if ui.button("Start").clicked() => {
+            self.scheduler.send(Command::StartRoutine(routine.clone()))
+          }
+          _ => {}
+        }
+        if routine.source.is_none() && ui.button("Remove").clicked() {
+          remove = Some(index);
+        }
+      });
+      if let Some(source) = &routine.source {
+        ui.label(format!("From {}", source.display()));
+      }
+      let Some((run, step)) = run.and_then(|run| Some((run, run.step_at(now)?))) else {
+        continue;
+      };
+      let left = (run.step_start(step + 1) - now).num_seconds().max(0) as u32;
+      ui.add(ProgressBar::new(run.progress(now)).text(format!(
+        "{}/{} {}, {} left",
+        step + 1,
+        run.routine.steps.len(),
+        run.routine.steps[step].label,
+        format_seconds(left)
+      )));
+    }
+    if !self.running.is_empty() {
+      ui.ctx()
+        .request_repaint_after(std::time::Duration::from_secs(1));
+    }
+    if let Some(index) = remove {
+      let removed = self.notifications.routines.remove(index);
+      self.running.retain(|run| run.routine.name != removed.name);
+      self
+        .scheduler
+        .send(Command::StopRoutine(removed.name.clone()));
+      match self.save_edit(ConfigEdit::RemoveRoutine(index)) {
+        Ok(()) => {
+          self.toasts.success(format!("Removed '{}'", removed.name));
+          self.saved();
+        }
+        Err(err) => {
+          tracing::error!(path = %self.path.display(), "Error removing the routine: {}", err);
+          self
+            .toasts
+            .error(format!("Error removing '{}': {}", removed.name, err));
+          self.notifications.routines.insert(index, removed);
+        }
+      }
+    }
+  }
+
+  fn render_add_routine(&mut self, ctx: &Context) {
+    let names: Vec<&str> = self
+      .notifications
+      .routines
+      .iter()
+      .map(|routine| routine.name.as_str())
+      .collect();
+    let Some(routine) = &mut self.routine_detail else {
+      return;
+    };
+    let mut save = false;
+    let mut cancel = false;
+    Window::new("Add a routine").show(ctx, |ui| {
+      ui.horizontal_top(|ui| {
+        ui.label("Name:");
+        ui.text_edit_singleline(&mut routine.name);
+      });
+      let mut remove = None;
+      for (index, step) in routine.steps.iter_mut().enumerate() {
+        ui.horizontal_top(|ui| {
+          ui.label(format!("{}.", index + 1));
+          ui.add(TextEdit::singleline(&mut step.label).hint_text("Step"));
+          ui.add(
+            DragValue::new(&mut step.seconds)
+              .range(1..=3600)
+              .suffix(" s"),
+          );
+          if ui.button("Remove").clicked() {
+            remove = Some(index);
+          }
+        });
+      }
+      if let Some(index) = remove {
+        routine.steps.remove(index);
+      }
+      if ui.button("Add step").clicked() {
+        routine.steps.push(RoutineStep::default());
+      }
+      let invalid = if routine.name.trim().is_empty() {
+        Some("The routine needs a name")
+      } else if names.contains(&routine.name.trim()) {
+        Some("There is already a routine with this name")
+      } else if routine.steps.is_empty() {
+        Some("The routine needs a step")
+      } else if routine
+        .steps
+        .iter()
+        .any(|step| step.label.trim().is_empty())
+      {
+        Some("Every step needs a label")
+      } else {
+        None
+      };
+      if let Some(invalid) = invalid {
+        ui.colored_label(Color32::RED, invalid);
+      }
+      ui.horizontal_top(|ui| {
+        save = ui
+          .add_enabled(invalid.is_none(), eframe::egui::Button::new("Save"))
+          .clicked();
+        cancel = ui.button("Cancel").clicked();
+      });
+    });
+    if cancel {
+      self.routine_detail = None;
+    }
+    if !save {
+      return;
+    }
+    let Some(mut routine) = self.routine_detail.take() else {
+      return;
+    };
+    routine.name = routine.name.trim().to_string();
+    let name = routine.name.clone();
+    self.notifications.routines.push(routine);
+    match self.save_edit(ConfigEdit::AddRoutine) {
+      Ok(()) => {
+        self.toasts.success(format!("Saved '{}'", name));
+        self.saved();
+      }
+      Err(err) => {
+        self.notifications.routines.pop();
+        tracing::error!(path = %self.path.display(), "Error saving the routine: {}", err);
+        self
+          .toasts
+          .error(format!("Error saving the routine: {}", err));
+      }
+    }
+  }
+
+  fn render_reminder(&mut self, ui: &mut Ui) {
+    ui.horizontal_top(|ui| {
+      ui.add(TextEdit::singleline(&mut self.reminder.label).hint_text("Remind me to..."));
+      ui.label("in");
+      ui.add(
+        DragValue::new(&mut self.reminder.minutes)
+          .range(1..=10080)
+          .suffix(" min"),
+      );
+      let add = ui.button("Remind me");
+      if add.clicked() && self.reminder.label.trim().is_empty() {
+        self.toasts.error("The reminder needs a label");
+      } else if add.clicked() {
+        self.add_reminder();
+      }
+    });
+  }
+
+  /// Saves a one-off reminder for the "Remind me" box.
+  fn add_reminder(&mut self) {
+    let label = self.reminder.label.trim();
+    if self
+      .notifications
+      .notifications
+      .iter()
+      .any(|n| n.label == label)
+    {
+      self.toasts.error(format!(
+        "There is already a notification called '{}'",
+        label
+      ));
+      return;
+    }
+    let at = (Local::now() + Duration::minutes(self.reminder.minutes)).naive_local();
+    let notification = NotificationDetails {
+      label: self.reminder.label.trim().to_string(),
+      at: at.with_second(0).and_then(|at| at.with_nanosecond(0)),
+      ..Default::default()
+    };
+    self.notifications.notifications.push(notification);
+    match self.save_edit(ConfigEdit::Add) {
+      Ok(()) => {
+        self
+          .toasts
+          .success(format!("Reminding you at {}", at.format("%H:%M")));
+        self.saved();
+        self.reminder = ReminderInput::default();
+      }
+      Err(err) => {
+        self.notifications.notifications.pop();
+        tracing::error!(path = %self.path.display(), "Error saving the reminder: {}", err);
+        self
+          .toasts
+          .error(format!("Error saving the reminder: {}", err));
+      }
+    }
+  }
+
+  /// The indices of the one-off reminders in the config file that were shown.
+  fn completed(&self) -> Vec<usize> {
+    self
+      .notifications
+      .notifications
+      .iter()
+      .enumerate()
+      .filter(|(_, n)| n.at.is_some() && n.source.is_none() && ended(n, &self.next_runs))
+      .map(|(index, _)| index)
+      .collect()
+  }
+
+  /// Removes the one-off reminders that were shown from the config file.
+  fn clear_completed(&mut self) {
+    let mut cleared = 0;
+    for index in self.completed().into_iter().rev() {
+      let removed = self.notifications.notifications.remove(index);
+      if let Err(err) = self.save_edit(ConfigEdit::Remove(index)) {
+        tracing::error!(path = %self.path.display(), "Error removing the notification: {}", err);
+        self
+          .toasts
+          .error(format!("Error removing '{}': {}", removed.label, err));
+        break;
+      }
+      cleared += 1;
+    }
+    if cleared > 0 {
+      self
+        .toasts
+        .success(format!("Removed {} completed reminders", cleared));
+    }
+    // Also puts back a reminder that couldn't be removed
+    self.saved();
+  }
+
+  fn render_quiet(&mut self, ui: &mut Ui) {
+    let local = |at: DateTime<Utc>| at.with_timezone(&Local).format("%H:%M").to_string();
+    ui.horizontal_top(|ui| {
+      if let Some(dnd_until) = self.dnd_until {
+        ui.label(format!("Do not disturb until {}", local(dnd_until)));
+        if ui.button("Turn off").clicked() {
+          self.scheduler.send(Command::DoNotDisturb(None));
+        }
+      } else {
+        if ui
+          .button("Do not disturb")
+          .on_hover_text("Hold back notifications for a while")
+          .clicked()
+        {
+          let until = Utc::now() + Duration::minutes(self.dnd_minutes);
+          self.scheduler.send(Command::DoNotDisturb(Some(until)));
+          self
+            .toasts
+            .info(format!("Do not disturb until {}", local(until)));
+        }
+        ComboBox::from_id_salt("dnd_minutes")
+          .selected_text(minutes_text(self.dnd_minutes))
+          .show_ui(ui, |ui| {
+            for minutes in DND_MINUTES {
+              ui.selectable_value(&mut self.dnd_minutes, minutes, minutes_text(minutes));
+            }
+          });
+      }
+      if let Some(until) = self
+        .quiet_until
+        .filter(|until| Some(*until) != self.dnd_until)
+      {
+        ui.label(RichText::new(format!("Quiet until {}", local(until))).weak());
+      }
+    });
+  }
+
+  fn render_card(&mut self, ui: &mut Ui) {
+    ScrollArea::vertical().show(ui, |ui| {
+      let mut remove = false;
+      let mut edit = false;
+      let mut selected_index = 0;
+      let mut commands = Vec::new();
+      for (index, notification) in self.notifications.notifications.iter().enumerate() {
+        let paused = notification
+          .job_id
+          .is_some_and(|job_id| self.paused.contains(&job_id));
+        let ended = !paused && ended(notification, &self.next_runs);
+        ui.add_space(10.);
+        ui.horizontal_top(|ui| {
+          let label = RichText::new(notification.label.as_str()).size(20.);
+          let label = match ended {
+            true => label.weak(),
+            false => label,
+          };
+          ui.label(label);
+          if let Some(job_id) = notification.job_id {
+            if paused {
+              if ui.button("Resume").clicked() {
+                commands.push(Command::Resume(job_id));
+              }
+            } else if ui.button("Pause").clicked() {
+              commands.push(Command::Pause(job_id));
+            }
+            if ui
+              .button("Snooze")
+              .on_hover_text("Show it again in 10 minutes")
+              .clicked()
+            {
+              commands.push(Command::Snooze(job_id, SNOOZE));
+            }
+            if ui.button("Show now").clicked() {
+              commands.push(Command::FireNow(job_id));
+            }
+            let last_fired = self.last_fired.get(&job_id);
+            let done = self.done.get(&job_id);
+            if last_fired.is_some_and(|fired| done.is_none_or(|done| done < fired))
+              && ui
+                .button("Done")
+                .on_hover_text("Mark the last reminder as done")
+                .clicked()
+            {
+              commands.push(Command::Done(job_id));
+            }
+          }
+          if notification.source.is_some() {
+            return;
+          }
+          let btn = ui.button("Remove");
+          if btn.clicked() {
+            remove = true;
+            selected_index = index;
+          }
+          let btn = ui.button("Edit");
+          if btn.clicked() {
+            edit = true;
+            selected_index = index;
+          }
+        });
+        if let Some(source) = &notification.source {
+          ui.label(format!("From {}", source.display()));
+        }
+        match (notification.at, &notification.follow_up) {
+          (Some(at), _) => ui.label(format!("Once at {}", at.format(REMINDER_FORMAT))),
+          (None, Some(follow_up)) => ui.label(format!(
+            "{} minutes after '{}' {}",
+            follow_up.minutes, follow_up.after, follow_up.when
+          )),
+          (None, None) => ui.label(notification.cron.as_str()),
+        };
+        if !notification.exclude_dates.is_empty() {
+          let files: Vec<String> = notification
+            .exclude_dates
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect();
+          ui.label(format!("Not shown on the dates in {}", files.join(", ")));
+        }
+        if let Some(active) = active_text(notification) {
+          ui.label(active);
+        }
+        if let Some(within) = &notification.within {
+          ui.label(format!(
+            "Once a day at a random time between {} and {}",
+            within.from, within.to
+          ));
+        }
+        if let Some(jitter) = notification.jitter {
+          ui.label(format!("Up to {} minutes earlier or later", jitter));
+        }
+        if let Some(escalation) = notification.escalate {
+          ui.label(format!(
+            "Repeats every {} minutes up to {} times until done",
+            escalation.every, escalation.times
+          ));
+        }
+        ui.horizontal_top(|ui| {
+          ui.label("Next notification at: ");
+          match (notification.schedule(), notification.zone()) {
+            (Ok(trigger), Ok(zone)) => {
+              let scheduled = notification
+                .job_id
+                .and_then(|job_id| self.next_runs.get(&job_id));
+              let next = match (scheduled, trigger) {
+                (Some(next), _) => *next,
+                (None, Trigger::Schedule(schedule)) => zone
+                  .upcoming_except(&schedule, Utc::now(), &notification.excluded)
+                  .next(),
+                (None, Trigger::Never) => None,
+              };
+              match (next, zone) {
+                _ if paused => ui.label("Paused"),
+                _ if ended && notification.at.is_some() => ui.label("Completed"),
+                _ if ended => ui.label("Ended"),
+                (Some(next), Zone::Local) => ui.label(zone.format(next)),
+                (Some(next), Zone::Named(_)) => ui.label(format!(
+                  "{} ({} local)",
+                  zone.format(next),
+                  Zone::Local.format(next)
+                )),
+                (None, _) => ui.label("Never"),
+              };
+            }
+            (Err(err), _) => {
+              ui.label(format!("Error: {}", err));
+            }
+            (_, Err(err)) => {
+              ui.label(format!("Error: {}", err));
+            }
+          }
+        });
+        let last_fired = notification
+          .job_id
+          .and_then(|job_id| self.last_fired.get(&job_id));
+        if let Some(at) = last_fired {
+          ui.label(format!("Last shown at: {}", Zone::Local.format(*at)));
+        }
+        ui.add_space(10.);
+        ui.separator();
+      }
+      for command in commands {
+        match command {
+          Command::Pause(job_id) => {
+            self.paused.insert(job_id);
+          }
+          Command::Resume(job_id) => {
+            self.paused.remove(&job_id);
+          }
+          Command::Done(job_id) => {
+            self.history_view.stale = true;
+            self.done.insert(job_id, Utc::now());
+          }
+          Command::Snooze(_, duration) => {
+            self.history_view.stale = true;
+            self
+              .toasts
+              .info(format!("Snoozed for {} minutes", duration.num_minutes()));
+          }
+          _ => {}
+        }
+        self.scheduler.send(command);
+      }
+      if remove
+        && self.notifications.notifications[selected_index]
+          .source
+          .is_some()
+      {
+        self.toasts.error(format!(
+          "Error removing '{}': {}",
+          self.notifications.notifications[selected_index].label,
+          YamlErrors::NotInConfigFile
+        ));
+      } else if remove {
+        let removed = self.notifications.notifications.remove(selected_index);
+        if let Err(err) = self.save_edit(ConfigEdit::Remove(selected_index)) {
+          tracing::error!(path = %self.path.display(), "Error removing the notification: {}", err);
+          self
+            .toasts
+            .error(format!("Error removing '{}': {}", removed.label, err));
+          self
+            .notifications
+            .notifications
+            .insert(selected_index, removed);
+        } else {
+          self.toasts.success(format!("Removed '{}'", removed.label));
+          self.saved();
+        }
+      }
+      if edit {
+        self.add_notification = true;
+        self.notification_detail = self.notifications.notifications[selected_index].clone();
+        self.dates = DatesInput::new(&self.notification_detail);
+        self.selected_index = Some(selected_index);
+      }
+    });
+  }
+}
+
+/// A bar per period, as high as the share of reminders that were done.
+/// Periods without any reminders are left empty.
+fn compliance_chart(ui: &mut Ui, periods: &[Period]) {
+  let (bar_width, height) = (14., 40.);
+  let (response, painter) = ui.allocate_painter(
+    Vec2::new(bar_width * periods.len() as f32, height),
+    Sense::hover(),
+  );
+  let rect = response.rect;
+  painter.rect_filled(rect, 2., ui.visuals().extreme_bg_color);
+  for (index, period) in periods.iter().enumerate() {
+    let Some(compliance) = period.compliance() else {
+      continue;
+    };
+    let left = rect.left() + bar_width * index as f32;
+    let top = rect.bottom() - (height * compliance as f32).max(1.);
+    let bar = eframe::egui::Rect::from_min_max(
+      [left + 1., top].into(),
+      [left + bar_width - 1., rect.bottom()].into(),
+    );
+    painter.rect_filled(bar, 1., Color32::from_rgb(80, 160, 90));
+  }
+  response.on_hover_text(
+    periods
+      .iter()
+      .filter(|period| period.shown > 0)
+      .map(|period| format!("{}: {} of {}", period.start, period.done, period.shown))
+      .collect::<Vec<_>>()
+      .join("\n"),
+  );
+}
+
+/// Whether the notification is past its end date or was shown as often as it
+/// should be.
+fn ended(
+  notification: &NotificationDetails,
+  next_runs: &HashMap<Uuid, Option<DateTime<Utc>>>,
+) -> bool {
+  let past_until = notification
+    .until
+    .is_some_and(|until| until < Local::now().date_naive());
+  let used_up = notification.limit().is_some()
+    && notification
+      .job_id
+      .and_then(|job_id| next_runs.get(&job_id))
+      .is_some_and(Option::is_none);
+  past_until || used_up
+}
+
+/// When the notification is shown, `None` if it is always shown.
+fn active_text(notification: &NotificationDetails) -> Option<String> {
+  let dates = match (notification.start, notification.until) {
+    (Some(start), Some(until)) => Some(format!("From {} until {}", start, until)),
+    (Some(start), None) => Some(format!("From {}", start)),
+    (None, Some(until)) => Some(format!("Until {}", until)),
+    (None, None) => None,
+  };
+  match (dates, notification.max_occurrences) {
+    (Some(dates), Some(limit)) => Some(format!("{}, at most {} times", dates, limit)),
+    (None, Some(limit)) => Some(format!("At most {} times", limit)),
+    (dates, None) => dates,
+  }
+}
+
+/// `90` as `1h 30m`.
+fn minutes_text(minutes: i64) -> String {
+  match (minutes / 60, minutes % 60) {
+    (0, minutes) => format!("{}m", minutes),
+    (hours, 0) => format!("{}h", hours),
+    (hours, minutes) => format!("{}h {}m", hours, minutes),
+  }
+}
+
+/// Why the notification can't be saved, `None` if it can.
+/// Why the notification can't be saved alongside the `others`, `None` if it
+/// can.
+fn validation_error(
+  notification: &NotificationDetails,
+  others: &[&NotificationDetails],
+) -> Option<String> {
+  if notification.label.trim().is_empty() {
+    return Some("The notification needs a label".to_string());
+  }
+  if let Err(err) = notification.schedule() {
+    return Some(format!("The cron is invalid: {}", err));
+  }
+  if let Some(follow_up) = &notification.follow_up {
+    if follow_up.after.trim().is_empty() {
+      return Some("Choose the notification to follow up".to_string());
+    }
+    if follow_up.after == notification.label {
+      return Some("A notification can't follow up itself".to_string());
+    }
+    let parent = |label: &str| others.iter().find(|other| other.label == label);
+    if parent(&follow_up.after).is_none() {
+      return Some(format!(
+        "There is no notification '{}' to follow up",
+        follow_up.after
+      ));
+    }
+    // Going up the notifications it follows, a chain longer than the others
+    // already goes round in a circle among them
+    let mut after = follow_up.after.as_str();
+    for _ in 0..others.len() {
+      match parent(after).and_then(|parent| parent.follow_up.as_ref()) {
+        Some(follow_up) if follow_up.after == notification.label => {
+          return Some(format!("'{}' already follows up this notification", after));
+        }
+        Some(follow_up) => after = follow_up.after.as_str(),
+        None => break,
+      }
+    }
+  }
+  if let Some(Err(err)) = notification.within.as_ref().map(TimeWindow::times) {
+    return Some(format!("The time window is invalid: {}", err));
+  }
+  if let (Some(start), Some(until)) = (notification.start, notification.until) {
+    if until < start {
+      return Some("The notification ends before it starts".to_string());
+    }
+  }
+  notification.zone().err().map(|err| err.to_string())
+}
+
+impl App for Notifier {
+  fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+    self.receive_events();
+    self.toasts.show(ctx);
+    CentralPanel::default().show(ctx, |ui| {
+      ui.label(RichText::new(format!("Config: {}", self.path.display())).weak());
+      if self.notifications.is_newer_than_supported() {
+        ui.colored_label(
+          Color32::YELLOW,
+          format!(
+            "The config file is version {} but this version of notifier only supports up to version {}. Update notifier to use all of its settings.",
+            self.notifications.version, CURRENT_VERSION
+          ),
+        );
+      }
+      for warning in self.notifications.warnings.iter() {
+        ui.colored_label(Color32::YELLOW, warning);
+      }
+      self.render_quiet(ui);
+      self.render_reminder(ui);
+      if self.notifications.notifications.is_empty()
+        && self.notifications.countdowns.is_empty()
+        && self.notifications.routines.is_empty()
+        && self.alarms.is_empty()
+      {
+        self.render_add_notification(ctx);
+      } else {
+        self.render_card(ui);
+        self.render_countdowns(ui);
+        self.render_routines(ui);
+        let btn = ui.button("Add Notification");
+        if btn.clicked() {
+          self.add_notification = true;
+        }
+        let btn = ui.button("Add Alarm");
+        if btn.clicked() {
+          self.add_alarm = true;
+        }
+        if ui.button("Add Countdown").clicked() {
+          self.countdown = Some(CountdownInput::default());
+        }
+        if ui.button("Add Routine").clicked() {
+          self.routine_detail = Some(Routine {
+            steps: vec![RoutineStep::default()],
+            ..Default::default()
+          });
+        }
+        if !self.completed().is_empty()
+          && ui
+            .button("Clear completed")
+            .on_hover_text("Remove the one-off reminders that were shown")
+            .clicked()
+        {
+          self.clear_completed();
+        }
+        if ui.button("History").clicked() {
+          self.history_view.open = true;
+          self.history_view.stale = true;
+        }
+        if ui.button("Stats").clicked() {
+          self.stats_view.open = true;
+          self.refresh_stats();
+        }
+        if self.add_notification {
+          self.render_add_notification(ctx);
+        }
+        if self.add_alarm {
+          self.render_add_alarm(ctx);
+        }
+        self.render_add_countdown(ctx);
+        self.render_add_routine(ctx);
+      }
+      if self.history_view.open {
+        self.render_history(ctx);
+      }
+      if self.stats_view.open {
+        self.render_stats(ctx);
+      }
+      self.render_reformat(ctx);
+    });
+  }
+
+  fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+    self.scheduler.shutdown();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_toasts_expire() {
+    let mut toasts = Toasts::default();
+    toasts.success("Saved");
+    toasts.error("Failed");
+    let shown_at = toasts.toasts[0].shown_at;
+    toasts.toasts[1].shown_at = shown_at;
+    assert_eq!(toasts.expire(shown_at), Some(TOAST_DURATION));
+    assert_eq!(
+      toasts.expire(shown_at + TOAST_DURATION),
+      Some(ERROR_TOAST_DURATION - TOAST_DURATION)
+    );
+    assert_eq!(toasts.toasts.len(), 1);
+    assert_eq!(toasts.toasts[0].kind, ToastKind::Error);
+    assert_eq!(toasts.expire(shown_at + ERROR_TOAST_DURATION), None);
+  }
+
+  #[test]
+  fn test_validation_error() {
+    let mut notification = NotificationDetails {
+      label: "Stretch".to_string(),
+      cron: "0 0 9 * * * *".to_string(),
+      ..Default::default()
+    };
+    assert_eq!(validation_error(&notification, &[]), None);
+    notification.timezone = Some("Mars/Olympus".to_string());
+    assert!(validation_error(&notification, &[]).is_some());
+    notification.cron = "every day".to_string();
+    assert!(validation_error(&notification, &[])
+      .unwrap()
+      .starts_with("The cron is invalid"));
+    notification.cron = "0 0 9 * * * *".to_string();
+    notification.timezone = None;
+    notification.start = "2026-07-14".parse().ok();
+    notification.until = "2026-07-01".parse().ok();
+    assert!(validation_error(&notification, &[]).is_some());
+    notification.label = " ".to_string();
+    assert_eq!(
+      validation_error(&notification, &[]),
+      Some("The notification needs a label".to_string())
+    );
+  }
+
+  #[test]
+  fn test_follow_up_validation() {
+    let follow_up = |label: &str, after: &str| NotificationDetails {
+      label: label.to_string(),
+      follow_up: Some(FollowUp {
+        after: after.to_string(),
+        minutes: 10,
+        when: FollowUpWhen::Shown,
+      }),
+      ..Default::default()
+    };
+    let bread = NotificationDetails {
+      label: "Put bread in".to_string(),
+      cron: "0 0 9 * * * *".to_string(),
+      ..Default::default()
+    };
+    let oven = follow_up("Check the oven", "Put bread in");
+    assert_eq!(validation_error(&oven, &[&bread]), None);
+    assert_eq!(
+      validation_error(&oven, &[]),
+      Some("There is no notification 'Put bread in' to follow up".to_string())
+    );
+    let cool = follow_up("Let it cool", "Check the oven");
+    assert_eq!(validation_error(&cool, &[&bread, &oven]), None);
+    let bread = follow_up("Put bread in", "Let it cool");
+    assert_eq!(
+      validation_error(&bread, &[&oven, &cool]),
+      Some("'Check the oven' already follows up this notification".to_string())
+    );
+  }
+}
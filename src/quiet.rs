@@ -0,0 +1,145 @@
+//! Quiet hours and do not disturb, during which notifications are dropped,
+//! deferred or shown anyway depending on their [`QuietPolicy`].
+//!
+//! [`QuietPolicy`]: crate::QuietPolicy
+
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+
+use crate::QuietHours;
+
+/// The most windows and do not disturb periods that are chained together when
+/// working out when the quiet time ends.
+const MAX_CHAINED: usize = 16;
+
+/// When notifications are held back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Quiet {
+  windows: Vec<(NaiveTime, NaiveTime)>,
+  /// When do not disturb turns off, `None` if it is off
+  pub dnd_until: Option<DateTime<Utc>>,
+}
+
+impl Quiet {
+  /// The quiet time for the configured quiet hours. Invalid windows are
+  /// returned as errors and left out.
+  pub fn new(hours: &[QuietHours]) -> (Self, Vec<String>) {
+    let mut errors = Vec::new();
+    let windows = hours
+      .iter()
+      .filter_map(|hours| {
+        hours
+          .window
+          .times()
+          .map_err(|err| errors.push(format!("Invalid quiet hours: {}", err)))
+          .ok()
+      })
+      .filter(|(from, to)| from != to)
+      .collect();
+    (
+      Self {
+        windows,
+        dnd_until: None,
+      },
+      errors,
+    )
+  }
+
+  /// When the quiet time that `now` is in ends, `None` if `now` isn't quiet.
+  /// Quiet hours are in the time zone `tz`.
+  pub fn until<Tz: TimeZone>(&self, now: DateTime<Utc>, tz: &Tz) -> Option<DateTime<Utc>> {
+    let mut end = now;
+    for _ in 0..MAX_CHAINED {
+      let dnd = self.dnd_until.filter(|until| *until > end);
+      let window = self
+        .windows
+        .iter()
+        .filter_map(|window| window_end(*window, end, tz))
+        .max();
+      match dnd.max(window) {
+        Some(next) => end = next,
+        None => break,
+      }
+    }
+    (end > now).then_some(end)
+  }
+}
+
+/// When the window `at` is in ends, `None` if it isn't in the window.
+fn window_end<Tz: TimeZone>(
+  (from, to): (NaiveTime, NaiveTime),
+  at: DateTime<Utc>,
+  tz: &Tz,
+) -> Option<DateTime<Utc>> {
+  let local = at.with_timezone(tz).naive_local();
+  let time = local.time();
+  let inside = if from < to {
+    from <= time && time < to
+  } else {
+    time >= from || time < to
+  };
+  if !inside {
+    return None;
+  }
+  let mut end = local.date().and_time(to);
+  if end <= local {
+    end += Duration::days(1);
+  }
+  // An end that is skipped by a daylight saving change is an hour later.
+  tz.from_local_datetime(&end)
+    .earliest()
+    .or_else(|| {
+      tz.from_local_datetime(&(end + Duration::hours(1)))
+        .earliest()
+    })
+    .map(|end| end.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::TimeWindow;
+
+  fn utc(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+  }
+
+  fn hours(from: &str, to: &str) -> QuietHours {
+    QuietHours {
+      window: TimeWindow {
+        from: from.to_string(),
+        to: to.to_string(),
+      },
+      source: None,
+    }
+  }
+
+  #[test]
+  fn test_quiet_hours_and_do_not_disturb() {
+    let (mut quiet, errors) = Quiet::new(&[hours("22:00", "07:00"), hours("12:00", "bad")]);
+    assert_eq!(errors.len(), 1);
+
+    assert_eq!(quiet.until(utc("2026-07-01T21:59:00Z"), &Utc), None);
+    assert_eq!(
+      quiet.until(utc("2026-07-01T22:00:00Z"), &Utc),
+      Some(utc("2026-07-02T07:00:00Z"))
+    );
+    assert_eq!(
+      quiet.until(utc("2026-07-02T06:30:00Z"), &Utc),
+      Some(utc("2026-07-02T07:00:00Z"))
+    );
+    assert_eq!(quiet.until(utc("2026-07-02T07:00:00Z"), &Utc), None);
+
+    quiet.dnd_until = Some(utc("2026-07-01T23:00:00Z"));
+    assert_eq!(
+      quiet.until(utc("2026-07-01T21:00:00Z"), &Utc),
+      Some(utc("2026-07-02T07:00:00Z")),
+      "do not disturb running into the quiet hours"
+    );
+    quiet.dnd_until = Some(utc("2026-07-02T08:00:00Z"));
+    assert_eq!(
+      quiet.until(utc("2026-07-02T06:00:00Z"), &Utc),
+      Some(utc("2026-07-02T08:00:00Z"))
+    );
+    assert_eq!(quiet.until(utc("2026-07-02T08:00:00Z"), &Utc), None);
+  }
+}
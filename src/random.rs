@@ -0,0 +1,51 @@
+//! Deterministic variation of when notifications are shown.
+//!
+//! The offsets only depend on the notification's label and the event they are
+//! for, so the next run shown in the GUI is the time the notification is
+//! actually shown and it doesn't change when notifier restarts.
+
+/// A seed for the offsets of the notification with `label`, FNV-1a.
+pub(crate) fn seed(label: &str) -> u64 {
+  label.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+    (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+  })
+}
+
+/// An offset in `0..range` for `key`, e.g. a day or the time of an event.
+pub(crate) fn offset(seed: u64, key: i64, range: i64) -> i64 {
+  if range <= 1 {
+    return 0;
+  }
+  (splitmix(seed ^ key as u64) % range as u64) as i64
+}
+
+/// An offset in `-max..=max` for `key`.
+pub(crate) fn jitter(seed: u64, key: i64, max: i64) -> i64 {
+  offset(seed, key, 2 * max + 1) - max
+}
+
+fn splitmix(value: u64) -> u64 {
+  let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+  z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_offsets_are_stable_and_in_range() {
+    let seed = seed("Take a break");
+    assert_eq!(seed, super::seed("Take a break"));
+    assert_ne!(seed, super::seed("Take a brake"));
+    let offsets: Vec<i64> = (0..1000).map(|day| offset(seed, day, 360)).collect();
+    assert!(offsets.iter().all(|offset| (0..360).contains(offset)));
+    assert!(offsets.iter().any(|offset| *offset < 60));
+    assert!(offsets.iter().any(|offset| *offset >= 300));
+    assert_eq!(offsets[42], offset(seed, 42, 360));
+    assert!((0..1000).all(|key| jitter(seed, key, 5).abs() <= 5));
+    assert_eq!(offset(seed, 1, 0), 0);
+  }
+}
@@ -0,0 +1,150 @@
+//! Running a [`Routine`], which shows a notification at the start of each step
+//! and once it is finished.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::Routine;
+
+/// A routine that was started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+  pub routine: Routine,
+  pub started: DateTime<Utc>,
+  /// How many of the step starts and the finish were announced
+  announced: usize,
+}
+
+impl Run {
+  pub fn new(routine: Routine, started: DateTime<Utc>) -> Self {
+    Self {
+      routine,
+      started,
+      announced: 0,
+    }
+  }
+
+  /// When the step at `index` starts, the index after the last step is when
+  /// the routine finishes.
+  pub fn step_start(&self, index: usize) -> DateTime<Utc> {
+    let seconds: i64 = self.routine.steps[..index.min(self.routine.steps.len())]
+      .iter()
+      .map(|step| step.seconds as i64)
+      .sum();
+    self.started + Duration::seconds(seconds)
+  }
+
+  pub fn ends(&self) -> DateTime<Utc> {
+    self.step_start(self.routine.steps.len())
+  }
+
+  /// The index of the step at `now`, `None` once the routine is finished.
+  pub fn step_at(&self, now: DateTime<Utc>) -> Option<usize> {
+    (0..self.routine.steps.len()).rfind(|index| self.step_start(*index) <= now && now < self.ends())
+  }
+
+  /// How much of the routine is done at `now`, from 0 to 1.
+  pub fn progress(&self, now: DateTime<Utc>) -> f32 {
+    let total = (self.ends() - self.started).num_milliseconds();
+    if total <= 0 {
+      return 1.;
+    }
+    ((now - self.started).num_milliseconds() as f32 / total as f32).clamp(0., 1.)
+  }
+
+  /// The text of the notification that is due at `now`, if any. When several
+  /// steps started since the last call only the latest one is announced.
+  pub(crate) fn due(&mut self, now: DateTime<Utc>) -> Option<String> {
+    let steps = self.routine.steps.len();
+    let reached = (0..=steps)
+      .take_while(|index| self.step_start(*index) <= now)
+      .count();
+    if reached <= self.announced {
+      return None;
+    }
+    self.announced = reached;
+    let name = &self.routine.name;
+    Some(match self.routine.steps.get(reached - 1) {
+      Some(step) => format!(
+        "{}: {} for {} ({}/{})",
+        name,
+        step.label,
+        format_seconds(step.seconds),
+        reached,
+        steps
+      ),
+      None => format!("{} is finished", name),
+    })
+  }
+
+  /// When the next notification is due, `None` once the finish was
+  /// announced.
+  pub(crate) fn next_due(&self) -> Option<DateTime<Utc>> {
+    (!self.is_finished()).then(|| self.step_start(self.announced))
+  }
+
+  /// Whether the finish was announced.
+  pub fn is_finished(&self) -> bool {
+    self.announced > self.routine.steps.len()
+  }
+}
+
+/// A duration such as `1:30` for minutes and seconds, or `45s`.
+pub fn format_seconds(seconds: u32) -> String {
+  match seconds {
+    0..=59 => format!("{}s", seconds),
+    _ => format!("{}:{:02}", seconds / 60, seconds % 60),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::RoutineStep;
+
+  fn step(label: &str, seconds: u32) -> RoutineStep {
+    RoutineStep {
+      label: label.to_string(),
+      seconds,
+    }
+  }
+
+  #[test]
+  fn test_announces_each_step_and_the_finish() {
+    let started: DateTime<Utc> = "2026-07-01T07:00:00Z".parse().unwrap();
+    let at = |seconds: i64| started + Duration::seconds(seconds);
+    let mut run = Run::new(
+      Routine {
+        name: "Stretch".to_string(),
+        steps: vec![
+          step("Neck rolls", 30),
+          step("Hamstrings", 90),
+          step("Back", 60),
+        ],
+        ..Default::default()
+      },
+      started,
+    );
+    assert_eq!(run.ends(), at(180));
+    assert_eq!(run.step_at(at(29)), Some(0));
+    assert_eq!(run.step_at(at(30)), Some(1));
+    assert_eq!(run.step_at(at(180)), None);
+    assert_eq!(run.progress(at(90)), 0.5);
+
+    assert_eq!(
+      run.due(at(0)).as_deref(),
+      Some("Stretch: Neck rolls for 30s (1/3)")
+    );
+    assert_eq!(run.due(at(10)), None);
+    assert_eq!(run.next_due(), Some(at(30)));
+    assert_eq!(
+      run.due(at(130)).as_deref(),
+      Some("Stretch: Back for 1:00 (3/3)"),
+      "only the latest step is announced"
+    );
+    assert!(!run.is_finished());
+    assert_eq!(run.due(at(180)).as_deref(), Some("Stretch is finished"));
+    assert!(run.is_finished());
+    assert_eq!(run.next_due(), None);
+    assert_eq!(run.due(at(200)), None);
+  }
+}
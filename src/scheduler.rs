@@ -0,0 +1,1339 @@
+//! The scheduler thread and the messages used to control it.
+//!
+//! The GUI sends [`Command`]s to the thread and gets [`Event`]s back so it can
+//! show when each notification fires next without re-reading the config.
+
+use std::{
+  collections::{HashMap, VecDeque},
+  path::PathBuf,
+  sync::{
+    mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    Arc, Weak,
+  },
+  thread::{self, JoinHandle},
+};
+
+use chrono::{DateTime, Duration, Local, Utc};
+use uuid::Uuid;
+
+use crate::{
+  history::{History, HistoryEntry, Outcome},
+  job_scheduler::{show_notification, Fire, Job, JobScheduler, Response, Zone},
+  quiet::Quiet,
+  routine::Run,
+  Countdown, FollowUp, FollowUpWhen, Level, NotificationDetails, Notifications, QuietHours,
+  QuietPolicy, Routine, SchedulerState,
+};
+
+/// The longest the scheduler thread sleeps for. The sleep doesn't account for
+/// changes to the system clock, so this bounds how late a notification can be
+/// after the clock is changed.
+const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How many notifications can wait to be dismissed at once. Showing a
+/// notification blocks its thread until it is dismissed on Linux, the ones due
+/// while this many are on screen are shown as the others are dismissed.
+const MAX_SHOWING: usize = 8;
+
+/// How often the scheduler thread checks for dismissed notifications while
+/// others are waiting to be shown.
+const DISPLAY_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// A request to the scheduler thread. Notifications are referred to by their
+/// `job_id`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+  /// Start scheduling a notification
+  Add(NotificationDetails),
+  /// Replace the notification with the same `job_id`
+  Update(NotificationDetails),
+  /// Stop scheduling a notification
+  Remove(Uuid),
+  /// Stop showing a notification until it is resumed
+  Pause(Uuid),
+  /// Show a paused notification again
+  Resume(Uuid),
+  /// Show the notification once more after the duration
+  Snooze(Uuid, Duration),
+  /// Show the notification straight away
+  FireNow(Uuid),
+  /// Mark the reminder as done, which also stops it repeating
+  Done(Uuid),
+  /// Turn do not disturb on until the time, or off with `None`
+  DoNotDisturb(Option<DateTime<Utc>>),
+  /// Replace the quiet hours
+  QuietHours(Vec<QuietHours>),
+  /// Replace the countdowns whose milestones are shown
+  Countdowns(Vec<Countdown>),
+  /// Start the routine, or start it again if it is running
+  StartRoutine(Routine),
+  /// Stop the running routine with the name
+  StopRoutine(String),
+  /// Stop the scheduler thread
+  Shutdown,
+}
+
+/// Something that happened in the scheduler thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+  /// A notification was shown, `job_id` is `None` for the summary of
+  /// notifications missed while the system was suspended and for the steps of
+  /// routines
+  Fired {
+    job_id: Option<Uuid>,
+    label: String,
+    at: DateTime<Utc>,
+  },
+  /// A command failed or a notification couldn't be shown
+  Error(String),
+  /// When a notification fires next changed, `None` if it is paused or won't
+  /// fire again
+  NextRunChanged {
+    job_id: Uuid,
+    next: Option<DateTime<Utc>>,
+  },
+  /// Quiet hours or do not disturb started or ended
+  QuietChanged {
+    /// When the quiet time ends, `None` if it isn't quiet
+    until: Option<DateTime<Utc>>,
+    /// When do not disturb turns off, `None` if it is off
+    dnd_until: Option<DateTime<Utc>>,
+  },
+  /// A routine was started
+  RoutineStarted(Run),
+  /// The routine with the name finished or was stopped
+  RoutineEnded(String),
+}
+
+type Wake = Arc<dyn Fn() + Send + Sync>;
+
+/// Handle to the scheduler thread, the thread is shut down when it is dropped.
+///
+/// When a `state_dir` is given the scheduler restores which notifications
+/// were paused or snoozed from it, saves its state there when it shuts down
+/// and records what happened to each notification in the history file.
+pub struct Scheduler {
+  /// The only strong reference, so the thread stops once this is dropped
+  commands: Arc<Sender<Command>>,
+  events: Receiver<Event>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+  /// Starts the scheduler thread with the notifications that have a
+  /// `job_id`. `wake` is called after events are sent, e.g. to repaint the
+  /// GUI.
+  pub fn spawn(
+    notifications: &Notifications,
+    state_dir: Option<PathBuf>,
+    wake: impl Fn() + Send + Sync + 'static,
+  ) -> Self {
+    let state_path = state_dir
+      .as_ref()
+      .map(|dir| dir.join(SchedulerState::FILE_NAME));
+    let history = state_dir.map(|dir| History::new(dir.join(History::FILE_NAME)));
+    let (commands, command_rx) = mpsc::channel();
+    let commands = Arc::new(commands);
+    let (event_tx, events) = mpsc::channel();
+    let quiet_hours = notifications.quiet_hours.clone();
+    let countdowns = notifications.countdowns.clone();
+    let notifications = notifications.notifications.clone();
+    let wake: Wake = Arc::new(wake);
+    let display = Display::new(
+      Arc::downgrade(&commands),
+      history.clone(),
+      event_tx.clone(),
+      wake.clone(),
+    );
+    let thread = thread::spawn(move || {
+      let mut worker = Worker {
+        display,
+        schedules: JobScheduler::new(),
+        next_runs: HashMap::new(),
+        labels: HashMap::new(),
+        state_keys: HashMap::new(),
+        shown_keys: HashMap::new(),
+        policies: HashMap::new(),
+        follow_ups: HashMap::new(),
+        quiet: Quiet::default(),
+        published_quiet: None,
+        deferred: Vec::new(),
+        countdowns: Vec::new(),
+        routines: Vec::new(),
+        state: state_path
+          .as_deref()
+          .map(SchedulerState::load)
+          .unwrap_or_default(),
+        history,
+        events: event_tx,
+        wake,
+      };
+      worker.quiet.dnd_until = worker.state.dnd_until;
+      worker.apply(Command::QuietHours(quiet_hours));
+      let last_run = worker.state.last_run;
+      worker.set_countdowns(&countdowns, last_run);
+      for notification in notifications {
+        worker.apply(Command::Add(notification));
+      }
+      worker.run(command_rx);
+      worker.display.finish();
+      tracing::info!("The scheduler stopped");
+      worker.state.deferred = worker
+        .deferred
+        .iter()
+        .filter_map(|fire| {
+          fire
+            .job_id
+            .and_then(|job_id| worker.state_keys.get(&job_id))
+        })
+        .cloned()
+        .collect();
+      worker.state.shown = worker
+        .shown_keys
+        .iter()
+        .filter_map(|(job_id, key)| Some((key.clone(), worker.schedules.shown(*job_id)?)))
+        .collect();
+      if let Some(path) = state_path {
+        worker.state.last_run = Some(Utc::now());
+        worker.state.prune(Utc::now());
+        if let Err(err) = worker.state.save(&path) {
+          tracing::error!(path = %path.display(), "Error saving the state: {}", err);
+        }
+      }
+    });
+    Self {
+      commands,
+      events,
+      thread: Some(thread),
+    }
+  }
+
+  /// Sends a command to the scheduler thread.
+  pub fn send(&self, command: Command) {
+    if self.commands.send(command).is_err() {
+      tracing::error!("The scheduler thread has stopped");
+    }
+  }
+
+  /// The events sent since the last call, without waiting for new ones.
+  pub fn events(&self) -> impl Iterator<Item = Event> + '_ {
+    self.events.try_iter()
+  }
+
+  /// Stops the scheduler thread and waits for it to finish showing the
+  /// notifications that are due and to save its state.
+  pub fn shutdown(&mut self) {
+    if let Some(thread) = self.thread.take() {
+      let _ = self.commands.send(Command::Shutdown);
+      if thread.join().is_err() {
+        tracing::error!("The scheduler thread panicked");
+      }
+    }
+  }
+}
+
+impl Drop for Scheduler {
+  fn drop(&mut self) {
+    self.shutdown();
+  }
+}
+
+struct Worker {
+  display: Display,
+  schedules: JobScheduler,
+  next_runs: HashMap<Uuid, Option<DateTime<Utc>>>,
+  labels: HashMap<Uuid, String>,
+  /// The keys of the notifications in the state, see
+  /// [`NotificationDetails::state_key`]. Countdowns have none as nothing
+  /// about them is saved.
+  state_keys: HashMap<Uuid, String>,
+  /// The keys of the notifications' shown counts in the state, see
+  /// [`NotificationDetails::shown_key`]
+  shown_keys: HashMap<Uuid, String>,
+  policies: HashMap<Uuid, QuietPolicy>,
+  /// The notifications that are shown after another one
+  follow_ups: HashMap<Uuid, FollowUp>,
+  quiet: Quiet,
+  /// The last [`Event::QuietChanged`] sent
+  published_quiet: Option<Event>,
+  /// Notifications held back until the quiet time is over
+  deferred: Vec<Fire>,
+  /// The jobs of the countdowns' milestones that are still to come
+  countdowns: Vec<Uuid>,
+  /// The routines that are running, they aren't affected by quiet hours as
+  /// they were started by hand
+  routines: Vec<Run>,
+  state: SchedulerState,
+  history: Option<History>,
+  events: Sender<Event>,
+  wake: Wake,
+}
+
+impl Worker {
+  fn run(&mut self, commands: Receiver<Command>) {
+    loop {
+      let now = Utc::now();
+      if self.quiet.dnd_until.is_some_and(|until| until <= now) {
+        tracing::info!("Do not disturb is over");
+        self.quiet.dnd_until = None;
+        self.state.dnd_until = None;
+      }
+      let quiet_until = self.quiet.until(now, &Local);
+      for fire in self.schedules.tick_due() {
+        self.dispatch(fire, quiet_until);
+      }
+      if quiet_until.is_none() {
+        for fire in std::mem::take(&mut self.deferred) {
+          self.show(fire);
+        }
+      }
+      self.run_routines(now);
+      self.display.start();
+      self.publish_next_runs();
+      self.publish_quiet(quiet_until);
+      let mut timeout = self
+        .schedules
+        .time_till_next_job()
+        .map_or(MAX_SLEEP, |next| next.min(MAX_SLEEP));
+      if let Some(next) = self.routines.iter().filter_map(Run::next_due).min() {
+        timeout = timeout.min((next - Utc::now()).to_std().unwrap_or_default());
+      }
+      if let Some(until) = quiet_until {
+        let till_end = (until - Utc::now()).to_std().unwrap_or_default();
+        timeout = timeout.min(till_end);
+      }
+      if self.display.is_waiting() {
+        timeout = timeout.min(DISPLAY_POLL);
+      }
+      match commands.recv_timeout(timeout) {
+        Ok(Command::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+        Ok(command) => self.apply(command),
+        Err(RecvTimeoutError::Timeout) => {}
+      }
+    }
+  }
+
+  fn apply(&mut self, command: Command) {
+    tracing::debug!(?command, "Received a command");
+    let missing = |job_id: Uuid| format!("There is no scheduled notification with id {}", job_id);
+    let result = match command {
+      Command::Add(notification) => job(&notification).map(|job| {
+        let job_id = self.schedules.replace(job);
+        self.restore(job_id, &notification);
+        self.labels.insert(job_id, notification.label.clone());
+        self.state_keys.insert(job_id, notification.state_key());
+        self.policies.insert(job_id, notification.quiet_policy());
+        self.set_follow_up(job_id, &notification);
+        self.catch_up(job_id, &notification);
+      }),
+      Command::Update(notification) => job(&notification).map(|job| {
+        let job_id = self.schedules.replace(job);
+        // A reminder moved to another time is shown again
+        let key = notification.shown_key();
+        if self
+          .shown_keys
+          .insert(job_id, key.clone())
+          .is_some_and(|old| old != key)
+        {
+          let shown = self.state.shown.get(&key).copied().unwrap_or_default();
+          self.schedules.set_shown(job_id, shown);
+        }
+        self.labels.insert(job_id, notification.label.clone());
+        self.state_keys.insert(job_id, notification.state_key());
+        self.policies.insert(job_id, notification.quiet_policy());
+        self.set_follow_up(job_id, &notification);
+      }),
+      Command::Remove(job_id) => {
+        self.next_runs.remove(&job_id);
+        self.policies.remove(&job_id);
+        self.follow_ups.remove(&job_id);
+        self.deferred.retain(|fire| fire.job_id != Some(job_id));
+        self.labels.remove(&job_id);
+        if let Some(key) = self.state_keys.remove(&job_id) {
+          self.state.paused.remove(&key);
+          self.state.snoozed.remove(&key);
+          self.state.follow_ups.remove(&key);
+        }
+        if let Some(key) = self.shown_keys.remove(&job_id) {
+          self.state.shown.remove(&key);
+        }
+        self
+          .schedules
+          .remove(job_id)
+          .then_some(())
+          .ok_or_else(|| missing(job_id))
+      }
+      Command::Pause(job_id) | Command::Resume(job_id) => {
+        let paused = matches!(command, Command::Pause(_));
+        if let Some(key) = self.state_keys.get(&job_id) {
+          if paused {
+            self.state.paused.insert(key.clone());
+          } else {
+            self.state.paused.remove(key);
+          }
+        }
+        self
+          .schedules
+          .pause(job_id, paused)
+          .then_some(())
+          .ok_or_else(|| missing(job_id))
+      }
+      Command::Snooze(job_id, duration) => {
+        let at = Utc::now() + duration;
+        if let (Some(label), Some(key)) = (self.labels.get(&job_id), self.state_keys.get(&job_id)) {
+          record(
+            &self.history,
+            HistoryEntry::new(label.as_str(), Outcome::Snoozed)
+              .detail(format!("for {} minutes", duration.num_minutes())),
+          );
+          self.state.snoozed.entry(key.clone()).or_default().push(at);
+        }
+        self
+          .schedules
+          .snooze(job_id, at)
+          .then_some(())
+          .ok_or_else(|| missing(job_id))
+      }
+      Command::FireNow(job_id) => match self.schedules.fire_now(job_id) {
+        Some(fire) => {
+          self.show(fire);
+          Ok(())
+        }
+        None => Err(missing(job_id)),
+      },
+      Command::Done(job_id) => match self.labels.get(&job_id) {
+        Some(label) => {
+          self.schedules.acknowledge(job_id);
+          if !self.countdowns.contains(&job_id) {
+            record(
+              &self.history,
+              HistoryEntry::new(label.as_str(), Outcome::Done),
+            );
+          }
+          self.follow_up(job_id, true);
+          Ok(())
+        }
+        None => Err(missing(job_id)),
+      },
+      Command::DoNotDisturb(until) => {
+        tracing::info!(?until, "Do not disturb changed");
+        self.quiet.dnd_until = until;
+        self.state.dnd_until = until;
+        Ok(())
+      }
+      Command::QuietHours(hours) => {
+        let (mut quiet, errors) = Quiet::new(&hours);
+        quiet.dnd_until = self.quiet.dnd_until;
+        self.quiet = quiet;
+        match errors.is_empty() {
+          true => Ok(()),
+          false => Err(errors.join(", ")),
+        }
+      }
+      Command::Countdowns(countdowns) => {
+        self.set_countdowns(&countdowns, None);
+        Ok(())
+      }
+      Command::StartRoutine(routine) => match routine.steps.is_empty() {
+        true => Err(format!("Routine '{}' has no steps", routine.name)),
+        false => {
+          tracing::info!(name = %routine.name, "Starting a routine");
+          self.routines.retain(|run| run.routine.name != routine.name);
+          let run = Run::new(routine, Utc::now());
+          self.routines.push(run.clone());
+          self.send(Event::RoutineStarted(run));
+          Ok(())
+        }
+      },
+      Command::StopRoutine(name) => {
+        let running = self.routines.len();
+        self.routines.retain(|run| run.routine.name != name);
+        match self.routines.len() < running {
+          true => {
+            tracing::info!(%name, "Stopped a routine");
+            self.send(Event::RoutineEnded(name));
+            Ok(())
+          }
+          false => Err(format!("Routine '{}' isn't running", name)),
+        }
+      }
+      Command::Shutdown => Ok(()),
+    };
+    if let Err(err) = result {
+      tracing::warn!("{}", err);
+      self.send(Event::Error(err));
+    }
+  }
+
+  /// Pauses and snoozes a newly added job like it was when the state was
+  /// saved.
+  fn restore(&mut self, job_id: Uuid, notification: &NotificationDetails) {
+    let key = notification.state_key();
+    if self.state.paused.contains(&key) {
+      self.schedules.pause(job_id, true);
+    }
+    let now = Utc::now();
+    let snoozed = self.state.snoozed.get(&key).into_iter().flatten();
+    let follow_ups = self.state.follow_ups.get(&key).into_iter().flatten();
+    for at in snoozed.chain(follow_ups) {
+      if *at > now {
+        self.schedules.snooze(job_id, *at);
+      }
+    }
+    let shown_key = notification.shown_key();
+    if let Some(shown) = self.state.shown.get(&shown_key) {
+      self.schedules.set_shown(job_id, *shown);
+    }
+    self.shown_keys.insert(job_id, shown_key);
+    if self.state.deferred.remove(&key) {
+      self.deferred.extend(self.schedules.fire_now(job_id));
+    }
+  }
+
+  fn set_follow_up(&mut self, job_id: Uuid, notification: &NotificationDetails) {
+    match &notification.follow_up {
+      Some(follow_up) => self.follow_ups.insert(job_id, follow_up.clone()),
+      None => self.follow_ups.remove(&job_id),
+    };
+  }
+
+  /// Schedules the follow-ups of the notification after it was shown, or
+  /// marked as done when `done` is set. The follow-ups that are only shown if
+  /// it isn't done are cancelled when it is done.
+  fn follow_up(&mut self, job_id: Uuid, done: bool) {
+    let Some(label) = self.labels.get(&job_id) else {
+      return;
+    };
+    let follow_ups: Vec<(Uuid, FollowUp)> = self
+      .follow_ups
+      .iter()
+      .filter(|(_, follow_up)| follow_up.after == *label)
+      .map(|(job_id, follow_up)| (*job_id, follow_up.clone()))
+      .collect();
+    for (follow_up_id, follow_up) in follow_ups {
+      let Some(follow_up_key) = self.state_keys.get(&follow_up_id) else {
+        continue;
+      };
+      match (follow_up.when, done) {
+        (FollowUpWhen::Shown | FollowUpWhen::NotDone, false) | (FollowUpWhen::Done, true) => {
+          // Shown the same way as a snoozed notification, and kept in the
+          // state so it is kept when notifier restarts
+          let at = Utc::now() + Duration::minutes(follow_up.minutes.max(1) as i64);
+          tracing::debug!(key = %follow_up_key, %at, "Scheduled a follow-up");
+          self
+            .state
+            .follow_ups
+            .entry(follow_up_key.clone())
+            .or_default()
+            .push(at);
+          self.schedules.snooze(follow_up_id, at);
+        }
+        (FollowUpWhen::NotDone, true) => {
+          tracing::debug!(key = %follow_up_key, "Cancelled a follow-up");
+          // Snoozes of the follow-up itself are kept
+          for at in self
+            .state
+            .follow_ups
+            .remove(follow_up_key)
+            .unwrap_or_default()
+          {
+            self.schedules.cancel_snooze(follow_up_id, at);
+          }
+        }
+        (FollowUpWhen::Shown, true) | (FollowUpWhen::Done, false) => {}
+      }
+    }
+  }
+
+  /// Shows a reminder with `at` that was due while notifier wasn't running,
+  /// that is since it last shut down.
+  fn catch_up(&mut self, job_id: Uuid, notification: &NotificationDetails) {
+    let (Some(at), Ok(zone), Some(last_run)) =
+      (notification.at, notification.zone(), self.state.last_run)
+    else {
+      return;
+    };
+    let now = Utc::now();
+    let at = zone.resolve(at);
+    if at <= last_run || at > now || self.schedules.shown(job_id) != Some(0) {
+      return;
+    }
+    tracing::info!(label = %notification.label, "Showing a reminder that was missed");
+    self.schedules.set_shown(job_id, 1);
+    if let Some(fire) = self.schedules.fire_now(job_id) {
+      let quiet_until = self.quiet.until(now, &Local);
+      self.dispatch(fire, quiet_until);
+    }
+  }
+
+  /// Shows the notification unless it is quiet, in which case it is dropped
+  /// or deferred depending on the notification's policy.
+  fn dispatch(&mut self, fire: Fire, quiet_until: Option<DateTime<Utc>>) {
+    let Some(until) = quiet_until else {
+      return self.show(fire);
+    };
+    let policy = fire
+      .job_id
+      .and_then(|job_id| self.policies.get(&job_id))
+      .copied()
+      .unwrap_or(QuietPolicy::Defer);
+    tracing::info!(label = %fire.body, %policy, %until, "A notification is due while it is quiet");
+    let label = self.configured_label(&fire);
+    match policy {
+      QuietPolicy::Bypass => self.show(fire),
+      QuietPolicy::Drop => {
+        if let Some(label) = label {
+          record(&self.history, HistoryEntry::new(label, Outcome::Dropped));
+        }
+      }
+      QuietPolicy::Defer => {
+        if let Some(label) = label {
+          record(
+            &self.history,
+            HistoryEntry::new(label, Outcome::Deferred).detail(format!(
+              "until {}",
+              until.with_timezone(&Local).format("%H:%M")
+            )),
+          );
+        }
+        self
+          .deferred
+          .retain(|deferred| deferred.job_id != fire.job_id);
+        self.deferred.push(fire);
+      }
+    }
+  }
+
+  /// The label of the configured notification that fired, `None` for the
+  /// summaries of missed notifications and the countdowns' milestones. Only
+  /// configured notifications are recorded in the history.
+  fn configured_label(&self, fire: &Fire) -> Option<String> {
+    fire
+      .job_id
+      .filter(|job_id| !self.countdowns.contains(job_id))
+      .and_then(|job_id| self.labels.get(&job_id))
+      .cloned()
+  }
+
+  fn show(&mut self, fire: Fire) {
+    let label = self.configured_label(&fire);
+    self.display.show(Popup {
+      job_id: fire.job_id,
+      body: fire.body.clone(),
+      label: label.clone(),
+      level: fire.level,
+      repeat: fire.repeat,
+    });
+    let at = Utc::now();
+    tracing::info!(
+      job_id = ?fire.job_id,
+      label = %fire.body,
+      level = %fire.level,
+      repeat = fire.repeat,
+      "Showing a notification"
+    );
+    if let Some(key) = fire.job_id.and_then(|job_id| self.state_keys.get(&job_id)) {
+      self.state.last_fired.insert(key.clone(), at);
+    }
+    if let (Some(job_id), 0) = (fire.job_id, fire.repeat) {
+      self.follow_up(job_id, false);
+    }
+    self.send(Event::Fired {
+      job_id: fire.job_id,
+      label: fire.body,
+      at,
+    });
+  }
+
+  /// Schedules a job for each milestone of the countdowns that is still to
+  /// come, replacing the ones of the previous countdowns. The milestones that
+  /// were due since `last_run`, while notifier wasn't running, are shown
+  /// straight away.
+  fn set_countdowns(&mut self, countdowns: &[Countdown], last_run: Option<DateTime<Utc>>) {
+    for job_id in std::mem::take(&mut self.countdowns) {
+      self.schedules.remove(job_id);
+      self.labels.remove(&job_id);
+      self.policies.remove(&job_id);
+      self.next_runs.remove(&job_id);
+    }
+    let now = Utc::now();
+    for countdown in countdowns {
+      let milestones = match countdown.milestones() {
+        Ok(milestones) => milestones,
+        Err(err) => {
+          let err = format!("Countdown '{}' is invalid: {}", countdown.label, err);
+          tracing::warn!("{}", err);
+          self.send(Event::Error(err));
+          continue;
+        }
+      };
+      for (schedule, text) in milestones {
+        let missed = last_run.is_some_and(|last_run| {
+          Zone::Local
+            .upcoming(&schedule, last_run)
+            .next()
+            .is_some_and(|at| at <= now)
+        });
+        if !missed && Zone::Local.upcoming(&schedule, now).next().is_none() {
+          continue;
+        }
+        let mut job = Job::new(schedule, text.clone());
+        job.level(countdown.level);
+        job.limit(1);
+        let job_id = self.schedules.add(job);
+        self.labels.insert(job_id, text);
+        self.policies.insert(job_id, countdown.level.quiet_policy());
+        self.countdowns.push(job_id);
+        if missed {
+          tracing::info!(label = %self.labels[&job_id], "Showing a countdown milestone that was missed");
+          if let Some(fire) = self.schedules.fire_now(job_id) {
+            let quiet_until = self.quiet.until(now, &Local);
+            self.dispatch(fire, quiet_until);
+          }
+        }
+      }
+    }
+  }
+
+  /// Shows the notifications of the routines' steps that started.
+  fn run_routines(&mut self, now: DateTime<Utc>) {
+    let due: Vec<String> = self
+      .routines
+      .iter_mut()
+      .filter_map(|run| run.due(now))
+      .collect();
+    for body in due {
+      self.announce(body);
+    }
+    let (finished, running) = std::mem::take(&mut self.routines)
+      .into_iter()
+      .partition(Run::is_finished);
+    self.routines = running;
+    for run in finished {
+      tracing::info!(name = %run.routine.name, "A routine finished");
+      self.send(Event::RoutineEnded(run.routine.name));
+    }
+  }
+
+  /// Shows the notification of a routine, which isn't recorded in the
+  /// history.
+  fn announce(&mut self, body: String) {
+    tracing::info!(label = %body, "Showing a routine step");
+    self.display.show(Popup {
+      job_id: None,
+      body: body.clone(),
+      label: None,
+      level: Level::Info,
+      repeat: 0,
+    });
+    self.send(Event::Fired {
+      job_id: None,
+      label: body,
+      at: Utc::now(),
+    });
+  }
+
+  fn publish_next_runs(&mut self) {
+    for (job_id, next) in self.schedules.next_runs() {
+      if self.next_runs.insert(job_id, next) != Some(next) {
+        tracing::debug!(%job_id, ?next, "Next run changed");
+        self.send(Event::NextRunChanged { job_id, next });
+      }
+    }
+  }
+
+  fn publish_quiet(&mut self, until: Option<DateTime<Utc>>) {
+    let event = Event::QuietChanged {
+      until,
+      dnd_until: self.quiet.dnd_until,
+    };
+    if self.published_quiet.as_ref() != Some(&event) {
+      self.published_quiet = Some(event.clone());
+      self.send(event);
+    }
+  }
+
+  fn send(&self, event: Event) {
+    if self.events.send(event).is_ok() {
+      (self.wake)();
+    }
+  }
+}
+
+/// A notification for a display thread to show.
+struct Popup {
+  job_id: Option<Uuid>,
+  body: String,
+  /// The label it is recorded under in the history, see
+  /// [`Worker::configured_label`]. Routine steps aren't recorded either.
+  label: Option<String>,
+  level: Level,
+  repeat: u32,
+}
+
+/// The thread of a notification on screen.
+struct Showing {
+  thread: JoinHandle<()>,
+  /// Disconnected once the notification is shown or failed to show, after
+  /// that the thread only waits for it to be dismissed
+  shown: Receiver<()>,
+}
+
+/// Shows notifications on at most [`MAX_SHOWING`] threads at once, the
+/// others wait in a queue.
+struct Display {
+  showing: Vec<Showing>,
+  waiting: VecDeque<Popup>,
+  /// For notifications marked as done from the notification itself, weak so
+  /// the display threads don't keep the scheduler thread running
+  commands: Weak<Sender<Command>>,
+  history: Option<History>,
+  events: Sender<Event>,
+  wake: Wake,
+}
+
+impl Display {
+  fn new(
+    commands: Weak<Sender<Command>>,
+    history: Option<History>,
+    events: Sender<Event>,
+    wake: Wake,
+  ) -> Self {
+    Self {
+      showing: Vec::new(),
+      waiting: VecDeque::new(),
+      commands,
+      history,
+      events,
+      wake,
+    }
+  }
+
+  /// Shows the notification once fewer than [`MAX_SHOWING`] are on screen.
+  fn show(&mut self, popup: Popup) {
+    self.waiting.push_back(popup);
+    self.start();
+  }
+
+  fn is_waiting(&self) -> bool {
+    !self.waiting.is_empty()
+  }
+
+  /// Joins the threads of the dismissed notifications and shows the waiting
+  /// ones in their place.
+  fn start(&mut self) {
+    self.join_finished();
+    while self.showing.len() < MAX_SHOWING {
+      let Some(popup) = self.waiting.pop_front() else {
+        break;
+      };
+      let showing = self.spawn(popup);
+      self.showing.push(showing);
+    }
+    if self.is_waiting() {
+      tracing::debug!(
+        waiting = self.waiting.len(),
+        "Waiting for notifications to be dismissed"
+      );
+    }
+  }
+
+  /// Shows the waiting notifications however many are on screen and waits
+  /// until all of them are shown. The threads of the notifications that are
+  /// still on screen wait for them to be dismissed until notifier exits.
+  fn finish(&mut self) {
+    for popup in std::mem::take(&mut self.waiting) {
+      let showing = self.spawn(popup);
+      self.showing.push(showing);
+    }
+    for showing in &self.showing {
+      let _ = showing.shown.recv();
+    }
+    self.join_finished();
+  }
+
+  fn join_finished(&mut self) {
+    let (finished, showing): (Vec<_>, _) = std::mem::take(&mut self.showing)
+      .into_iter()
+      .partition(|showing| showing.thread.is_finished());
+    self.showing = showing;
+    for showing in finished {
+      if showing.thread.join().is_err() {
+        tracing::error!("A display thread panicked");
+      }
+    }
+  }
+
+  fn spawn(&self, popup: Popup) -> Showing {
+    let events = self.events.clone();
+    let wake = self.wake.clone();
+    let commands = self.commands.clone();
+    let history = self.history.clone().filter(|_| popup.label.is_some());
+    let (shown_tx, shown) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || {
+      let Popup {
+        job_id,
+        body,
+        label,
+        level,
+        repeat,
+      } = popup;
+      // Nothing is recorded without a label
+      let label = label.unwrap_or_default();
+      let entry = |outcome| HistoryEntry::new(label.as_str(), outcome);
+      let on_shown = || {
+        let entry = entry(Outcome::Shown);
+        let entry = match repeat {
+          0 => entry,
+          repeat => entry.detail(format!("repeat {}", repeat)),
+        };
+        record(&history, entry);
+        drop(shown_tx);
+      };
+      match show_notification(&body, level, on_shown) {
+        Ok(Response::Done) => {
+          // Recorded by the scheduler thread with the follow-ups
+          if let Some((job_id, commands)) = job_id.zip(commands.upgrade()) {
+            let _ = commands.send(Command::Done(job_id));
+          }
+        }
+        Ok(Response::Dismissed) => record(&history, entry(Outcome::Dismissed)),
+        Ok(Response::Unknown) => {}
+        Err(err) => {
+          record(&history, entry(Outcome::Failed).detail(err.to_string()));
+          tracing::error!(label = %body, "Error displaying notification: {}", err);
+          let _ = events.send(Event::Error(format!(
+            "Error displaying notification: {}",
+            err
+          )));
+          wake();
+        }
+      }
+    });
+    Showing { thread, shown }
+  }
+}
+
+fn record(history: &Option<History>, entry: HistoryEntry) {
+  if let Some(history) = history {
+    if let Err(err) = history.append(&entry) {
+      tracing::error!("Error writing to the history: {}", err);
+    }
+  }
+}
+
+fn job(notification: &NotificationDetails) -> Result<Job, String> {
+  let invalid = |err: &dyn std::fmt::Display| {
+    format!("Notification '{}' is invalid: {}", notification.label, err)
+  };
+  let job_id = notification
+    .job_id
+    .ok_or_else(|| format!("Notification '{}' has no job id", notification.label))?;
+  let schedule = notification.schedule().map_err(|err| invalid(&err))?;
+  let zone = notification.zone().map_err(|err| invalid(&err))?;
+  let mut job = Job::new_with_id(schedule, notification.label.clone(), job_id);
+  job.time_zone(zone);
+  job.level(notification.level);
+  job.except(notification.excluded.clone());
+  job.dates(notification.start, notification.until);
+  if let Some(limit) = notification.limit() {
+    job.limit(limit);
+  }
+  if let Some(jitter) = notification.jitter {
+    job.jitter(jitter);
+  }
+  if let Some(within) = &notification.within {
+    let (from, to) = within.times().map_err(|err| invalid(&err))?;
+    job.window(from, to);
+  }
+  if let Some(escalation) = notification.escalate {
+    job.escalation(escalation);
+  }
+  Ok(job)
+}
+
+/// Gives the notifications in `reloaded` the `job_id` of the matching
+/// notification in `current` and returns the commands that bring the
+/// scheduler from `current` to `reloaded`.
+///
+/// Notifications from the same file with the same label are the same
+/// notification, if anything else about them changed it is updated.
+pub fn sync(current: &[NotificationDetails], reloaded: &mut [NotificationDetails]) -> Vec<Command> {
+  let same =
+    |a: &NotificationDetails, b: &NotificationDetails| a.source == b.source && a.label == b.label;
+  let mut unmatched: Vec<&NotificationDetails> = current.iter().collect();
+  let mut commands = Vec::new();
+  for notification in reloaded.iter_mut() {
+    match unmatched.iter().position(|old| same(old, notification)) {
+      Some(index) => {
+        let old = unmatched.remove(index);
+        notification.job_id = old.job_id;
+        if notification.job_id.is_none() {
+          notification.job_id = Some(Uuid::new_v4());
+          commands.push(Command::Add(notification.clone()));
+        } else if old != notification {
+          commands.push(Command::Update(notification.clone()));
+        }
+      }
+      None => {
+        notification.job_id = Some(Uuid::new_v4());
+        commands.push(Command::Add(notification.clone()));
+      }
+    }
+  }
+  commands.extend(
+    unmatched
+      .into_iter()
+      .filter_map(|old| old.job_id)
+      .map(Command::Remove),
+  );
+  commands
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn notification(label: &str, cron: &str) -> NotificationDetails {
+    NotificationDetails {
+      label: label.to_string(),
+      cron: cron.to_string(),
+      ..Default::default()
+    }
+  }
+
+  fn worker() -> Worker {
+    let (events, _) = mpsc::channel();
+    let wake: Wake = Arc::new(|| ());
+    Worker {
+      display: Display::new(Weak::new(), None, events.clone(), wake.clone()),
+      schedules: JobScheduler::new(),
+      next_runs: HashMap::new(),
+      labels: HashMap::new(),
+      state_keys: HashMap::new(),
+      shown_keys: HashMap::new(),
+      policies: HashMap::new(),
+      follow_ups: HashMap::new(),
+      quiet: Quiet::default(),
+      published_quiet: None,
+      deferred: Vec::new(),
+      countdowns: Vec::new(),
+      routines: Vec::new(),
+      state: SchedulerState::default(),
+      history: None,
+      events,
+      wake,
+    }
+  }
+
+  #[test]
+  fn test_removing_forgets_the_shown_count() {
+    let mut worker = worker();
+    let mut stretch = notification("Stretch", "0 0 9 * * * *");
+    stretch.max_occurrences = Some(3);
+    stretch.job_id = Some(Uuid::new_v4());
+    worker.state.shown.insert("Stretch".to_string(), 2);
+    worker.apply(Command::Add(stretch.clone()));
+    assert_eq!(worker.schedules.shown(stretch.job_id.unwrap()), Some(2));
+
+    worker.apply(Command::Remove(stretch.job_id.unwrap()));
+    stretch.job_id = Some(Uuid::new_v4());
+    worker.apply(Command::Add(stretch.clone()));
+    assert_eq!(worker.schedules.shown(stretch.job_id.unwrap()), Some(0));
+  }
+
+  #[test]
+  fn test_shown_count_of_a_reminder_is_keyed_on_its_time() {
+    let mut worker = worker();
+    let mut tea = NotificationDetails {
+      label: "Tea".to_string(),
+      at: Some("2030-01-01T16:00:00".parse().unwrap()),
+      job_id: Some(Uuid::new_v4()),
+      ..Default::default()
+    };
+    worker
+      .state
+      .shown
+      .insert("Tea at 2030-01-01T16:00:00".to_string(), 1);
+    worker.apply(Command::Add(tea.clone()));
+    assert_eq!(worker.schedules.shown(tea.job_id.unwrap()), Some(1));
+
+    tea.at = Some("2030-01-02T16:00:00".parse().unwrap());
+    worker.apply(Command::Update(tea.clone()));
+    assert_eq!(
+      worker.schedules.shown(tea.job_id.unwrap()),
+      Some(0),
+      "a reminder moved to another time is shown again"
+    );
+
+    tea.job_id = Some(Uuid::new_v4());
+    tea.at = Some("2030-01-01T16:00:00".parse().unwrap());
+    worker.apply(Command::Add(tea.clone()));
+    assert_eq!(worker.schedules.shown(tea.job_id.unwrap()), Some(1));
+  }
+
+  #[test]
+  fn test_state_keeps_notifications_from_other_files_apart() {
+    let mut worker = worker();
+    let mut main = notification("Stretch", "0 0 9 * * * *");
+    main.job_id = Some(Uuid::new_v4());
+    let mut work = main.clone();
+    work.job_id = Some(Uuid::new_v4());
+    work.source = Some(PathBuf::from("work.yaml"));
+    worker.apply(Command::Add(main.clone()));
+    worker.apply(Command::Add(work.clone()));
+    worker.apply(Command::Pause(work.job_id.unwrap()));
+    assert_eq!(
+      worker.state.paused.iter().collect::<Vec<_>>(),
+      ["work.yaml: Stretch"]
+    );
+
+    let mut worker = Worker {
+      state: worker.state,
+      ..self::worker()
+    };
+    worker.apply(Command::Add(main.clone()));
+    worker.apply(Command::Add(work.clone()));
+    let next_runs: HashMap<Uuid, Option<DateTime<Utc>>> =
+      worker.schedules.next_runs().into_iter().collect();
+    assert!(next_runs[&main.job_id.unwrap()].is_some());
+    assert_eq!(next_runs[&work.job_id.unwrap()], None, "still paused");
+  }
+
+  #[test]
+  fn test_catches_up_reminders_due_since_the_last_run() {
+    let mut worker = worker();
+    let now = Utc::now();
+    worker.state.last_run = Some(now - Duration::hours(2));
+    // Defer what is caught up instead of showing it
+    worker.quiet.dnd_until = Some(now + Duration::hours(1));
+    let reminder = |label: &str, hours: i64| NotificationDetails {
+      label: label.to_string(),
+      at: Some((now - Duration::hours(hours)).naive_utc()),
+      timezone: Some("UTC".to_string()),
+      job_id: Some(Uuid::new_v4()),
+      ..Default::default()
+    };
+    let missed = reminder("Missed", 1);
+    let old = reminder("Old", 3);
+    worker.apply(Command::Add(missed.clone()));
+    worker.apply(Command::Add(old.clone()));
+    assert_eq!(worker.schedules.shown(missed.job_id.unwrap()), Some(1));
+    assert_eq!(
+      worker.schedules.shown(old.job_id.unwrap()),
+      Some(0),
+      "reminders due before the last run aren't shown again"
+    );
+    assert_eq!(worker.deferred.len(), 1);
+  }
+
+  #[test]
+  fn test_follow_ups() {
+    let mut notifications = Notifications {
+      notifications: vec![
+        notification("Put bread in", "0 0 9 * * * *"),
+        NotificationDetails {
+          label: "Check the oven".to_string(),
+          follow_up: Some(FollowUp {
+            after: "Put bread in".to_string(),
+            minutes: 20,
+            when: FollowUpWhen::Shown,
+          }),
+          ..Default::default()
+        },
+        NotificationDetails {
+          label: "Did you eat?".to_string(),
+          follow_up: Some(FollowUp {
+            after: "Put bread in".to_string(),
+            minutes: 60,
+            when: FollowUpWhen::NotDone,
+          }),
+          ..Default::default()
+        },
+      ],
+      ..Default::default()
+    };
+    notifications.assign_job_ids();
+    let ids: Vec<Uuid> = notifications
+      .notifications
+      .iter()
+      .map(|n| n.job_id.unwrap())
+      .collect();
+    let mut worker = worker();
+    for notification in notifications.notifications {
+      worker.apply(Command::Add(notification));
+    }
+    let next_run = |worker: &Worker, job_id: Uuid| {
+      worker
+        .schedules
+        .next_runs()
+        .into_iter()
+        .find(|(id, _)| *id == job_id)
+        .and_then(|(_, next)| next)
+    };
+    assert_eq!(next_run(&worker, ids[1]), None);
+
+    let shown = Utc::now();
+    worker.follow_up(ids[0], false);
+    let oven = next_run(&worker, ids[1]).unwrap();
+    assert!((oven - shown - Duration::minutes(20)).num_seconds().abs() < 5);
+    assert!(next_run(&worker, ids[2]).is_some());
+    assert_eq!(worker.state.follow_ups.len(), 2);
+    assert!(worker.state.snoozed.is_empty());
+
+    let snoozed = shown + Duration::hours(3);
+    worker
+      .state
+      .snoozed
+      .insert("Did you eat?".to_string(), vec![snoozed]);
+    worker.schedules.snooze(ids[2], snoozed);
+    worker.apply(Command::Done(ids[0]));
+    assert_eq!(next_run(&worker, ids[1]), Some(oven));
+    assert_eq!(
+      next_run(&worker, ids[2]),
+      Some(snoozed),
+      "a snooze of the follow-up is kept"
+    );
+    assert!(worker.state.snoozed.contains_key("Did you eat?"));
+  }
+
+  #[test]
+  fn test_countdown_milestones() {
+    let mut worker = worker();
+    let countdown = Countdown {
+      label: "Release".to_string(),
+      date: Local::now().date_naive() + Duration::days(10),
+      milestones: vec![30, 7, 1, 0],
+      time: None,
+      level: Level::Warning,
+      source: None,
+    };
+    worker.apply(Command::Countdowns(vec![countdown]));
+    let mut labels: Vec<&str> = worker
+      .countdowns
+      .iter()
+      .map(|job_id| worker.labels[job_id].as_str())
+      .collect();
+    labels.sort();
+    assert_eq!(
+      labels,
+      [
+        "Release in 7 days",
+        "Release is today",
+        "Release is tomorrow"
+      ],
+      "the milestone 30 days before has passed"
+    );
+
+    worker.apply(Command::Countdowns(Vec::new()));
+    assert!(worker.countdowns.is_empty());
+    assert!(worker.labels.is_empty());
+    assert!(worker.schedules.next_runs().is_empty());
+  }
+
+  #[test]
+  fn test_history_only_records_configured_notifications() {
+    let path = std::env::temp_dir().join(format!("notifier-history-{}.jsonl", Uuid::new_v4()));
+    let mut worker = worker();
+    worker.history = Some(History::new(path.clone()));
+    let now = Utc::now();
+    let mut stretch = notification("Stretch", "0 0 9 * * * *");
+    stretch.job_id = Some(Uuid::new_v4());
+    worker.apply(Command::Add(stretch.clone()));
+    worker.apply(Command::Countdowns(vec![Countdown {
+      label: "Release".to_string(),
+      date: Local::now().date_naive() + Duration::days(10),
+      milestones: vec![7],
+      time: None,
+      level: Level::Info,
+      source: None,
+    }]));
+    let milestone = worker.countdowns[0];
+    let quiet_until = Some(now + Duration::hours(1));
+    let fires = [
+      worker.schedules.fire_now(stretch.job_id.unwrap()).unwrap(),
+      worker.schedules.fire_now(milestone).unwrap(),
+      Fire {
+        job_id: None,
+        body: "You missed 3 notifications".to_string(),
+        level: Level::Info,
+        repeat: 0,
+      },
+    ];
+    for fire in fires {
+      worker.dispatch(fire, quiet_until);
+    }
+    let entries = worker.history.as_ref().unwrap().read().unwrap();
+    let _ = std::fs::remove_file(path);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].label, "Stretch");
+    assert_eq!(entries[0].outcome, Outcome::Deferred);
+  }
+
+  #[test]
+  fn test_catches_up_countdown_milestones_due_since_the_last_run() {
+    let mut worker = worker();
+    let now = Utc::now();
+    // Defer what is caught up instead of showing it
+    worker.quiet.dnd_until = Some(now + Duration::hours(1));
+    let countdowns = [Countdown {
+      label: "Release".to_string(),
+      date: Local::now().date_naive(),
+      milestones: vec![3, 1],
+      time: None,
+      level: Level::Warning,
+      source: None,
+    }];
+    worker.set_countdowns(&countdowns, Some(now - Duration::days(2)));
+    let deferred: Vec<&str> = worker
+      .deferred
+      .iter()
+      .map(|fire| fire.body.as_str())
+      .collect();
+    assert_eq!(
+      deferred,
+      ["Release is tomorrow"],
+      "the milestone before the last run was shown then"
+    );
+    assert_eq!(worker.policies[&worker.countdowns[0]], QuietPolicy::Defer);
+
+    worker.apply(Command::Countdowns(countdowns.to_vec()));
+    assert!(worker.countdowns.is_empty(), "only caught up once");
+    assert!(worker.policies.is_empty());
+  }
+
+  #[test]
+  fn test_sync_keeps_ids_and_diffs() {
+    let mut current = Notifications {
+      notifications: vec![
+        notification("Keep", "0 0 9 * * * *"),
+        notification("Change", "0 0 10 * * * *"),
+        notification("Drop", "0 0 11 * * * *"),
+      ],
+      ..Default::default()
+    };
+    current.assign_job_ids();
+    let ids: Vec<Uuid> = current
+      .notifications
+      .iter()
+      .map(|n| n.job_id.unwrap())
+      .collect();
+    let mut reloaded = vec![
+      notification("Keep", "0 0 9 * * * *"),
+      notification("Change", "0 30 10 * * * *"),
+      notification("New", "0 0 12 * * * *"),
+    ];
+    let commands = sync(&current.notifications, &mut reloaded);
+    assert_eq!(reloaded[0].job_id, Some(ids[0]));
+    assert_eq!(reloaded[1].job_id, Some(ids[1]));
+    assert!(reloaded[2].job_id.is_some());
+    assert_eq!(
+      commands,
+      vec![
+        Command::Update(reloaded[1].clone()),
+        Command::Add(reloaded[2].clone()),
+        Command::Remove(ids[2]),
+      ]
+    );
+  }
+}
@@ -0,0 +1,31 @@
+//! JSON Schema of the config file so editors and linters can validate it.
+
+use schemars::schema_for;
+
+use crate::Notifications;
+
+/// The JSON Schema describing the config file.
+pub fn json_schema() -> serde_json::Value {
+  schema_for!(Notifications).to_value()
+}
+
+/// The JSON Schema describing the config file as pretty printed JSON.
+pub fn json_schema_string() -> String {
+  serde_json::to_string_pretty(&json_schema()).expect("a JSON value is always serialisable")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_schema_describes_notifications() {
+    let schema = json_schema();
+    assert_eq!(schema["title"], "Notifications");
+    assert!(schema["properties"]["notifications"].is_object());
+    assert!(schema["required"]
+      .as_array()
+      .unwrap()
+      .contains(&"notifications".into()));
+  }
+}
@@ -0,0 +1,121 @@
+//! What the scheduler remembers between runs.
+//!
+//! The state is keyed by the notification label, prefixed with the file it was
+//! merged in from, so it still applies after the config file is reloaded and
+//! the notifications get new job ids. See
+//! [`NotificationDetails::state_key`](crate::NotificationDetails::state_key).
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs,
+  path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The state of the scheduler that is saved when it shuts down.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SchedulerState {
+  /// When each notification was last shown
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub last_fired: BTreeMap<String, DateTime<Utc>>,
+  /// The notifications that are paused
+  #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+  pub paused: BTreeSet<String>,
+  /// When snoozed notifications are shown again
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub snoozed: BTreeMap<String, Vec<DateTime<Utc>>>,
+  /// When scheduled follow-ups are shown, apart from the snoozes so marking
+  /// the notification they follow as done only cancels these
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub follow_ups: BTreeMap<String, Vec<DateTime<Utc>>>,
+  /// The notifications held back until the quiet time is over
+  #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+  pub deferred: BTreeSet<String>,
+  /// How many times the notifications with a `max_occurrences` or an `at`
+  /// were shown, reminders with an `at` are keyed on the label and the time
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub shown: BTreeMap<String, u32>,
+  /// When do not disturb turns off
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub dnd_until: Option<DateTime<Utc>>,
+  /// When the scheduler last shut down
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub last_run: Option<DateTime<Utc>>,
+}
+
+impl SchedulerState {
+  /// The name of the state file in the state directory.
+  pub const FILE_NAME: &'static str = "state.yaml";
+
+  /// Reads the state saved at `path`, a missing or unreadable file is an
+  /// empty state.
+  pub fn load(path: &Path) -> Self {
+    let Ok(content) = fs::read_to_string(path) else {
+      return Self::default();
+    };
+    serde_yaml::from_str(&content).unwrap_or_else(|err| {
+      tracing::warn!(path = %path.display(), "Ignoring the saved state: {}", err);
+      Self::default()
+    })
+  }
+
+  /// Writes the state to `path`. The file is replaced in one step so a
+  /// shutdown while saving doesn't leave a partial file behind.
+  pub fn save(&self, path: &Path) -> std::io::Result<()> {
+    let content = serde_yaml::to_string(self).map_err(std::io::Error::other)?;
+    let temp = path.with_extension("yaml.tmp");
+    fs::write(&temp, content)?;
+    fs::rename(&temp, path)
+  }
+
+  /// Forgets snoozes and follow-ups that are due before `now`, they were
+  /// either shown or missed while notifier wasn't running, and do not disturb
+  /// if it is over.
+  pub fn prune(&mut self, now: DateTime<Utc>) {
+    self.dnd_until = self.dnd_until.filter(|until| *until > now);
+    for pending in [&mut self.snoozed, &mut self.follow_ups] {
+      for times in pending.values_mut() {
+        times.retain(|at| *at > now);
+      }
+      pending.retain(|_, times| !times.is_empty());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_save_load_and_prune() {
+    let utc = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let mut state = SchedulerState::default();
+    state
+      .last_fired
+      .insert("Stretch".to_string(), utc("2026-07-01T09:00:00Z"));
+    state.paused.insert("Drink water".to_string());
+    state.snoozed.insert(
+      "Stretch".to_string(),
+      vec![utc("2026-07-01T09:10:00Z"), utc("2026-07-01T10:10:00Z")],
+    );
+    state.follow_ups.insert(
+      "Check the oven".to_string(),
+      vec![utc("2026-07-01T09:20:00Z")],
+    );
+    let path = std::env::temp_dir().join(format!("notifier-state-{}.yaml", std::process::id()));
+    state.save(&path).unwrap();
+    assert_eq!(SchedulerState::load(&path), state);
+    fs::remove_file(&path).unwrap();
+
+    state.dnd_until = Some(utc("2026-07-01T10:00:00Z"));
+    state.prune(utc("2026-07-01T09:30:00Z"));
+    assert_eq!(state.snoozed["Stretch"], vec![utc("2026-07-01T10:10:00Z")]);
+    assert!(state.dnd_until.is_some());
+    assert!(state.follow_ups.is_empty());
+    state.prune(utc("2026-07-01T11:00:00Z"));
+    assert!(state.snoozed.is_empty());
+    assert_eq!(state.dnd_until, None);
+  }
+}
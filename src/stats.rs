@@ -0,0 +1,285 @@
+//! Habit statistics worked out from the history.
+//!
+//! A reminder is complied with when it is marked as done after it was shown.
+//! Days it wasn't shown on, e.g. weekends for a weekday reminder, don't count
+//! towards compliance and don't break a streak.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use chrono::{Datelike, Duration, NaiveDate, TimeZone};
+
+use crate::history::{HistoryEntry, Outcome};
+
+/// How often a reminder was shown and done in a day or week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+  /// The day, or the Monday of the week
+  pub start: NaiveDate,
+  pub shown: usize,
+  pub done: usize,
+}
+
+impl Period {
+  fn new(start: NaiveDate) -> Self {
+    Self {
+      start,
+      shown: 0,
+      done: 0,
+    }
+  }
+
+  /// The times it was shown but not done.
+  pub fn ignored(&self) -> usize {
+    self.shown.saturating_sub(self.done)
+  }
+
+  /// The share of the times it was shown that it was done, `None` if it
+  /// wasn't shown.
+  pub fn compliance(&self) -> Option<f64> {
+    (self.shown > 0).then(|| self.done.min(self.shown) as f64 / self.shown as f64)
+  }
+}
+
+/// The statistics of a single reminder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HabitStats {
+  pub label: String,
+  /// The days it was shown or done on, oldest first
+  pub days: Vec<Period>,
+  /// Days in a row up to today it was done on
+  pub current_streak: usize,
+  pub best_streak: usize,
+}
+
+impl HabitStats {
+  /// The days from `from` to `to`, including days without any activity.
+  pub fn daily(&self, from: NaiveDate, to: NaiveDate) -> Vec<Period> {
+    from
+      .iter_days()
+      .take_while(|day| *day <= to)
+      .map(|day| {
+        self
+          .days
+          .iter()
+          .find(|period| period.start == day)
+          .copied()
+          .unwrap_or_else(|| Period::new(day))
+      })
+      .collect()
+  }
+
+  /// The weeks from the one `from` is in to the one `to` is in.
+  pub fn weekly(&self, from: NaiveDate, to: NaiveDate) -> Vec<Period> {
+    let mut weeks: Vec<Period> = week_start(from)
+      .iter_weeks()
+      .take_while(|week| *week <= to)
+      .map(Period::new)
+      .collect();
+    for day in self.days.iter() {
+      if let Some(week) = weeks
+        .iter_mut()
+        .find(|week| week.start == week_start(day.start))
+      {
+        week.shown += day.shown;
+        week.done += day.done;
+      }
+    }
+    weeks
+  }
+
+  /// The compliance over the days from `from` to `to`.
+  pub fn compliance(&self, from: NaiveDate, to: NaiveDate) -> Option<f64> {
+    let mut total = Period::new(from);
+    for day in self.daily(from, to) {
+      total.shown += day.shown;
+      total.done += day.done;
+    }
+    total.compliance()
+  }
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+  day - Duration::days(day.weekday().num_days_from_monday() as i64)
+}
+
+/// The statistics of the reminders in `entries` whose label is one of
+/// `labels`, the ones in the config, sorted by label. Days are in the time
+/// zone `tz` and `today` is the last day of the current streak.
+pub fn habit_stats<Tz: TimeZone>(
+  entries: &[HistoryEntry],
+  labels: &[&str],
+  tz: &Tz,
+  today: NaiveDate,
+) -> Vec<HabitStats> {
+  let mut by_label: BTreeMap<&str, BTreeMap<NaiveDate, Period>> = BTreeMap::new();
+  for entry in entries {
+    if !labels.contains(&entry.label.as_str()) {
+      continue;
+    }
+    let counter = match entry.outcome {
+      Outcome::Shown => |period: &mut Period| period.shown += 1,
+      Outcome::Done => |period: &mut Period| period.done += 1,
+      Outcome::Failed
+      | Outcome::Snoozed
+      | Outcome::Dismissed
+      | Outcome::Deferred
+      | Outcome::Dropped => continue,
+    };
+    let day = entry.at.with_timezone(tz).date_naive();
+    counter(
+      by_label
+        .entry(entry.label.as_str())
+        .or_default()
+        .entry(day)
+        .or_insert_with(|| Period::new(day)),
+    );
+  }
+  by_label
+    .into_iter()
+    .map(|(label, days)| {
+      let days: Vec<Period> = days.into_values().collect();
+      let (current_streak, best_streak) = streaks(&days, today);
+      HabitStats {
+        label: label.to_string(),
+        days,
+        current_streak,
+        best_streak,
+      }
+    })
+    .collect()
+}
+
+/// The current and the best streak. Today only adds to the current streak
+/// once it is done, so a streak isn't broken before the day is over.
+fn streaks(days: &[Period], today: NaiveDate) -> (usize, usize) {
+  let mut best = 0;
+  let mut current = 0;
+  for day in days.iter().filter(|day| day.start <= today) {
+    if day.done > 0 {
+      current += 1;
+      best = best.max(current);
+    } else if day.start < today {
+      current = 0;
+    }
+  }
+  (current, best)
+}
+
+/// The statistics as CSV with a row per reminder and day.
+pub fn to_csv(stats: &[HabitStats]) -> String {
+  let mut csv = String::from("label,date,shown,done,ignored\n");
+  for habit in stats {
+    let label = if habit.label.contains([',', '"', '\n']) {
+      format!("\"{}\"", habit.label.replace('"', "\"\""))
+    } else {
+      habit.label.clone()
+    };
+    for day in habit.days.iter() {
+      let _ = writeln!(
+        csv,
+        "{},{},{},{},{}",
+        label,
+        day.start,
+        day.shown,
+        day.done,
+        day.ignored()
+      );
+    }
+  }
+  csv
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Utc;
+
+  use super::*;
+
+  fn entry(at: &str, label: &str, outcome: Outcome) -> HistoryEntry {
+    HistoryEntry {
+      at: at.parse().unwrap(),
+      ..HistoryEntry::new(label, outcome)
+    }
+  }
+
+  fn date(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn test_streaks_and_compliance() {
+    let entries = vec![
+      // Monday, done
+      entry("2026-07-06T09:00:00Z", "Stretch", Outcome::Shown),
+      entry("2026-07-06T09:01:00Z", "Stretch", Outcome::Done),
+      entry("2026-07-06T10:00:00Z", "Stretch", Outcome::Shown),
+      // Tuesday, ignored
+      entry("2026-07-07T09:00:00Z", "Stretch", Outcome::Shown),
+      entry("2026-07-07T09:00:30Z", "Stretch", Outcome::Dismissed),
+      // Wednesday and Thursday done, nothing on Friday and the weekend
+      entry("2026-07-08T09:00:00Z", "Stretch", Outcome::Shown),
+      entry("2026-07-08T09:05:00Z", "Stretch", Outcome::Done),
+      entry("2026-07-09T09:00:00Z", "Stretch", Outcome::Shown),
+      entry("2026-07-09T09:05:00Z", "Stretch", Outcome::Done),
+      // Monday, not done yet
+      entry("2026-07-13T09:00:00Z", "Stretch", Outcome::Shown),
+      entry("2026-07-13T09:00:00Z", "Drink water", Outcome::Shown),
+      // Removed from the config
+      entry("2026-07-13T10:00:00Z", "Water the plants", Outcome::Shown),
+    ];
+    let labels = ["Stretch", "Drink water"];
+    let stats = habit_stats(&entries, &labels, &Utc, date("2026-07-13"));
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0].label, "Drink water");
+    let stretch = &stats[1];
+    assert_eq!((stretch.current_streak, stretch.best_streak), (2, 2));
+    assert_eq!(
+      stretch.compliance(date("2026-07-06"), date("2026-07-12")),
+      Some(3. / 5.)
+    );
+    assert_eq!(
+      stretch.compliance(date("2026-07-10"), date("2026-07-12")),
+      None
+    );
+    assert_eq!(
+      stretch.weekly(date("2026-07-08"), date("2026-07-13")),
+      vec![
+        Period {
+          start: date("2026-07-06"),
+          shown: 5,
+          done: 3
+        },
+        Period {
+          start: date("2026-07-13"),
+          shown: 1,
+          done: 0
+        },
+      ]
+    );
+    assert_eq!(
+      stretch.daily(date("2026-07-10"), date("2026-07-12")).len(),
+      3
+    );
+
+    let stats = habit_stats(&entries, &labels, &Utc, date("2026-07-14"));
+    assert_eq!((stats[1].current_streak, stats[1].best_streak), (0, 2));
+  }
+
+  #[test]
+  fn test_csv() {
+    let entries = vec![
+      entry("2026-07-06T09:00:00Z", "Stretch, then walk", Outcome::Shown),
+      entry("2026-07-06T09:01:00Z", "Stretch, then walk", Outcome::Done),
+      entry("2026-07-06T10:00:00Z", "Stretch, then walk", Outcome::Shown),
+    ];
+    assert_eq!(
+      to_csv(&habit_stats(
+        &entries,
+        &["Stretch, then walk"],
+        &Utc,
+        date("2026-07-06")
+      )),
+      "label,date,shown,done,ignored\n\"Stretch, then walk\",2026-07-06,2,1,1\n"
+    );
+  }
+}
@@ -1,52 +1,780 @@
-use std::{fs, path::PathBuf};
-
-use serde::{Deserialize, Serialize};
-use thiserror::Error;
-use uuid::Uuid;
-
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default, Clone)]
-pub struct Notifications {
-  pub notifications: Vec<NotificationDetails>,
-}
-
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default, Clone, Hash)]
-pub struct NotificationDetails {
-  pub label: String,
-  pub cron: String,
-  pub level: String,
-  #[serde(skip)]
-  pub job_id: Option<Uuid>,
-}
-
-#[derive(Debug, Error)]
-pub enum YamlErrors {
-  #[error("Error trying to read the config file")]
-  CouldNotReadConfigFile,
-  #[error("Error trying to deserialize the config file")]
-  CouldNotDeserializeFile,
-  #[error("Error trying to save to the config file")]
-  CouldNotSaveToFile,
-}
-
-pub fn load_contents(path: &PathBuf) -> Result<String, YamlErrors> {
-  std::fs::read_to_string(path).map_err(|_| YamlErrors::CouldNotReadConfigFile)
-}
-
-pub fn load_file_and_deserialise(path: &PathBuf) -> Result<Notifications, YamlErrors> {
-  let config_content = load_contents(path)?;
-  if config_content.trim().is_empty() {
-    return Ok(Notifications::default());
-  }
-  let notifications: Notifications =
-    serde_yaml::from_str(&config_content).map_err(|_| YamlErrors::CouldNotDeserializeFile)?;
-  Ok(notifications)
-}
-
-pub fn save_contents(path: &PathBuf, notify: &Notifications) -> Result<(), YamlErrors> {
-  fs::write(
-    path,
-    serde_yaml::to_string(notify).map_err(|_| YamlErrors::CouldNotSaveToFile)?,
-  )
-  .map_err(|_| YamlErrors::CouldNotSaveToFile)?;
-  Ok(())
-}
+use std::{fs, path::PathBuf};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use cron::Schedule;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+  calendar::Calendar,
+  codec::Format,
+  job_scheduler::{Trigger, Zone},
+  migrations::{self, CURRENT_VERSION},
+  Errors,
+};
+
+/// The notifier config file.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Notifications {
+  /// The schema version of the file, older versions are migrated when loaded
+  #[serde(default = "unversioned")]
+  pub version: u32,
+  /// Other config files whose notifications are merged into this one, relative
+  /// to this file
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub include: Vec<PathBuf>,
+  /// Times of day when notifications are held back, see
+  /// [`NotificationDetails::quiet`]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub quiet_hours: Vec<QuietHours>,
+  /// Files of dates, or iCalendar files, on which none of the notifications in
+  /// this file are shown, relative to this file
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub exclude_dates: Vec<PathBuf>,
+  /// The scheduled notifications
+  pub notifications: Vec<NotificationDetails>,
+  /// Dates that are counted down to, with notifications on the way
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub countdowns: Vec<Countdown>,
+  /// Sequences of steps that are started by hand, e.g. from the GUI or with
+  /// `notifier routine`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub routines: Vec<Routine>,
+  /// Problems found while merging the included config files
+  #[serde(skip)]
+  pub warnings: Vec<String>,
+}
+
+/// Files written before the version key was added are version 1.
+fn unversioned() -> u32 {
+  1
+}
+
+impl Default for Notifications {
+  fn default() -> Self {
+    Self {
+      version: CURRENT_VERSION,
+      include: Vec::new(),
+      quiet_hours: Vec::new(),
+      exclude_dates: Vec::new(),
+      notifications: Vec::new(),
+      countdowns: Vec::new(),
+      routines: Vec::new(),
+      warnings: Vec::new(),
+    }
+  }
+}
+
+impl Notifications {
+  /// Whether the file was written by a newer version of notifier.
+  pub fn is_newer_than_supported(&self) -> bool {
+    self.version > CURRENT_VERSION
+  }
+
+  /// Only the notifications defined in the config file itself, without the
+  /// ones merged in from other files.
+  pub fn own(&self) -> Notifications {
+    Notifications {
+      quiet_hours: own_items(&self.quiet_hours),
+      notifications: own_items(&self.notifications),
+      countdowns: own_items(&self.countdowns),
+      routines: own_items(&self.routines),
+      warnings: Vec::new(),
+      ..self.clone()
+    }
+  }
+
+  /// Gives every notification without one a `job_id` so it can be referred
+  /// to when talking to the scheduler.
+  pub fn assign_job_ids(&mut self) {
+    for notification in self.notifications.iter_mut() {
+      notification.job_id.get_or_insert_with(Uuid::new_v4);
+    }
+  }
+}
+
+/// An entry of a config file that can be merged in from another file.
+pub(crate) trait Sourced {
+  /// The file the entry was merged in from, `None` if it is defined in the
+  /// main config file
+  fn source(&self) -> Option<&PathBuf>;
+  fn set_source(&mut self, source: Option<PathBuf>);
+}
+
+macro_rules! impl_sourced {
+  ($($ty:ty),*) => {
+    $(impl Sourced for $ty {
+      fn source(&self) -> Option<&PathBuf> {
+        self.source.as_ref()
+      }
+
+      fn set_source(&mut self, source: Option<PathBuf>) {
+        self.source = source;
+      }
+    })*
+  };
+}
+
+impl_sourced!(NotificationDetails, QuietHours, Countdown, Routine);
+
+/// The entries defined in the config file itself.
+fn own_items<T: Sourced + Clone>(items: &[T]) -> Vec<T> {
+  items
+    .iter()
+    .filter(|item| item.source().is_none())
+    .cloned()
+    .collect()
+}
+
+/// The index in [`own_items`] of the entry at `index`.
+fn own_index<T: Sourced>(items: &[T], index: usize) -> usize {
+  items[..index.min(items.len())]
+    .iter()
+    .filter(|item| item.source().is_none())
+    .count()
+}
+
+/// How important a notification is.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default, Clone, Copy, Hash, JsonSchema)]
+pub enum Level {
+  #[default]
+  Info,
+  Warning,
+  Critical,
+}
+
+impl Level {
+  /// What to do with a notification of this level that is due during quiet
+  /// hours, unless it says otherwise.
+  pub fn quiet_policy(self) -> QuietPolicy {
+    match self {
+      Level::Critical => QuietPolicy::Bypass,
+      Level::Info | Level::Warning => QuietPolicy::Defer,
+    }
+  }
+
+  /// The next level up, `Critical` stays `Critical`.
+  pub fn raised(self) -> Level {
+    match self {
+      Level::Info => Level::Warning,
+      Level::Warning | Level::Critical => Level::Critical,
+    }
+  }
+}
+
+impl std::fmt::Display for Level {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    std::fmt::Debug::fmt(self, f)
+  }
+}
+
+/// Shows a notification again until it is marked as done.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Hash, JsonSchema)]
+pub struct Escalation {
+  /// Minutes between the repeats
+  pub every: u32,
+  /// The most times the notification is repeated
+  pub times: u32,
+  /// Raise the level of each repeat by one, critical notifications stay on
+  /// screen until they are closed
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub raise_urgency: bool,
+}
+
+impl Default for Escalation {
+  fn default() -> Self {
+    Self {
+      every: 5,
+      times: 3,
+      raise_urgency: false,
+    }
+  }
+}
+
+/// A time of day, in the system's time zone, when notifications are held
+/// back.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Hash, JsonSchema)]
+pub struct QuietHours {
+  #[serde(flatten)]
+  pub window: TimeWindow,
+  /// The file the quiet hours were merged in from, `None` if they are defined
+  /// in the main config file
+  #[serde(skip)]
+  pub source: Option<PathBuf>,
+}
+
+/// A named sequence of steps, e.g. a stretching routine. Once it is started a
+/// notification is shown at the start of each step and when it is finished.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default, Hash, JsonSchema)]
+pub struct Routine {
+  pub name: String,
+  pub steps: Vec<RoutineStep>,
+  /// The file the routine was merged in from, `None` if it is defined in the
+  /// main config file
+  #[serde(skip)]
+  pub source: Option<PathBuf>,
+}
+
+/// A step of a [`Routine`].
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Hash, JsonSchema)]
+pub struct RoutineStep {
+  pub label: String,
+  /// How long the step lasts in seconds
+  pub seconds: u32,
+}
+
+impl Default for RoutineStep {
+  fn default() -> Self {
+    Self {
+      label: String::new(),
+      seconds: 30,
+    }
+  }
+}
+
+/// A time of day, the window goes past midnight when `to` is before `from`. A
+/// notification's window is in its time zone.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Hash, JsonSchema)]
+pub struct TimeWindow {
+  /// The start of the window, e.g. `10:00`
+  pub from: String,
+  /// The end of the window, e.g. `16:00`
+  pub to: String,
+}
+
+impl TimeWindow {
+  /// The start and end of the window.
+  pub fn times(&self) -> Result<(NaiveTime, NaiveTime), String> {
+    Ok((time_of_day(&self.from)?, time_of_day(&self.to)?))
+  }
+}
+
+fn time_of_day(time: &str) -> Result<NaiveTime, String> {
+  NaiveTime::parse_from_str(time.trim(), "%H:%M")
+    .map_err(|_| format!("'{}' is not a time like 22:00", time))
+}
+
+/// What happens to a notification that is due during quiet hours or while do
+/// not disturb is on.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Hash, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietPolicy {
+  /// Don't show it
+  Drop,
+  /// Show it once the quiet time is over
+  Defer,
+  /// Show it anyway
+  Bypass,
+}
+
+impl std::fmt::Display for QuietPolicy {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    std::fmt::Debug::fmt(self, f)
+  }
+}
+
+/// Shows a notification some time after another one.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Hash, JsonSchema)]
+pub struct FollowUp {
+  /// The label of the notification this one follows
+  pub after: String,
+  /// Minutes after the other notification
+  pub minutes: u32,
+  #[serde(default)]
+  pub when: FollowUpWhen,
+}
+
+/// What has to happen to the notification that is followed up.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default, Clone, Copy, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FollowUpWhen {
+  /// It was shown
+  #[default]
+  Shown,
+  /// It was marked as done
+  Done,
+  /// It was shown and wasn't marked as done before the follow-up is due
+  NotDone,
+}
+
+impl std::fmt::Display for FollowUpWhen {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      FollowUpWhen::Shown => "is shown",
+      FollowUpWhen::Done => "is marked as done",
+      FollowUpWhen::NotDone => "is shown and not done",
+    })
+  }
+}
+
+/// A date that is counted down to, e.g. a release day or a holiday, and
+/// counted up from once it passed.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Hash, JsonSchema)]
+pub struct Countdown {
+  pub label: String,
+  pub date: NaiveDate,
+  /// The days before the date a notification is shown on, 0 for the date
+  /// itself and negative for days after it
+  #[serde(default = "default_milestones")]
+  pub milestones: Vec<i64>,
+  /// The time of day the notifications are shown at in the system's time
+  /// zone, 09:00 by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub time: Option<String>,
+  #[serde(default)]
+  pub level: Level,
+  /// The file the countdown was merged in from, `None` if it is defined in
+  /// the main config file
+  #[serde(skip)]
+  pub source: Option<PathBuf>,
+}
+
+fn default_milestones() -> Vec<i64> {
+  vec![30, 7, 1, 0]
+}
+
+impl Countdown {
+  /// The days from `today` to the date, negative once it passed.
+  pub fn days_left(&self, today: NaiveDate) -> i64 {
+    (self.date - today).num_days()
+  }
+
+  /// The text for `days` before the date.
+  pub fn text(&self, days: i64) -> String {
+    match days {
+      2.. => format!("{} in {} days", self.label, days),
+      1 => format!("{} is tomorrow", self.label),
+      0 => format!("{} is today", self.label),
+      -1 => format!("{} was yesterday", self.label),
+      _ => format!("{} was {} days ago", self.label, -days),
+    }
+  }
+
+  /// The next milestone from `today` on, in days before the date.
+  pub fn next_milestone(&self, today: NaiveDate) -> Option<i64> {
+    let days = self.days_left(today);
+    self
+      .milestones
+      .iter()
+      .copied()
+      .filter(|milestone| *milestone <= days)
+      .max()
+  }
+
+  /// The schedule and text of each milestone's notification.
+  pub fn milestones(&self) -> Result<Vec<(Schedule, String)>, String> {
+    let time = time_of_day(self.time.as_deref().unwrap_or("09:00"))?;
+    self
+      .milestones
+      .iter()
+      .map(|days| {
+        let at = (self.date - chrono::Duration::days(*days)).and_time(time);
+        let schedule = at_schedule(at).map_err(|err| err.to_string())?;
+        Ok((schedule, self.text(*days)))
+      })
+      .collect()
+  }
+}
+
+/// A schedule that only matches `at`.
+fn at_schedule(at: NaiveDateTime) -> Result<Schedule, cron::error::Error> {
+  at.format("%-S %-M %-H %-d %-m * %Y").to_string().parse()
+}
+
+/// A notification shown on a cron schedule.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default, Clone, Hash, JsonSchema)]
+pub struct NotificationDetails {
+  /// The text shown in the notification
+  pub label: String,
+  /// When to show the notification: `sec min hour day-of-month month day-of-week year`.
+  /// Not needed for a reminder with `at`
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub cron: String,
+  /// Show the notification once at this date and time, e.g.
+  /// `2026-11-03T14:00:00`, instead of on the cron schedule
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub at: Option<NaiveDateTime>,
+  /// Show the notification after another one instead of on the cron schedule
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub follow_up: Option<FollowUp>,
+  #[serde(default)]
+  pub level: Level,
+  /// The IANA time zone the cron is evaluated in, e.g. `America/New_York`.
+  /// Defaults to the system's time zone
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub timezone: Option<String>,
+  /// The first date the notification is shown on, e.g. `2026-07-01`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub start: Option<NaiveDate>,
+  /// The last date the notification is shown on
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub until: Option<NaiveDate>,
+  /// The most times the notification is shown
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_occurrences: Option<u32>,
+  /// Show the notification up to this many minutes earlier or later than the
+  /// cron says, so it is less predictable
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub jitter: Option<u32>,
+  /// Show the notification once at a random time in this window on each day
+  /// the cron matches, every day if there is no cron
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub within: Option<TimeWindow>,
+  /// Show the notification again until it is marked as done
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub escalate: Option<Escalation>,
+  /// What to do when the notification is due during quiet hours. Critical
+  /// notifications are shown anyway and others are deferred by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub quiet: Option<QuietPolicy>,
+  /// Files of dates, or iCalendar files, on which the notification isn't
+  /// shown, e.g. public holidays. Relative to the file the notification is in
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub exclude_dates: Vec<PathBuf>,
+  /// The dates of `exclude_dates` and of the `exclude_dates` of the files it
+  /// is in, read when the config is loaded
+  #[serde(skip)]
+  pub excluded: Calendar,
+  #[serde(skip)]
+  pub job_id: Option<Uuid>,
+  /// The file the notification was merged in from, `None` if it is defined in
+  /// the main config file
+  #[serde(skip)]
+  pub source: Option<PathBuf>,
+}
+
+impl NotificationDetails {
+  /// The time zone the cron is evaluated in.
+  pub fn zone(&self) -> Result<Zone, Errors> {
+    self.timezone.as_deref().map_or(Ok(Zone::Local), str::parse)
+  }
+
+  /// When the notification is shown, a reminder with `at` is a schedule that
+  /// only matches that time. With `within` and no cron it matches every day.
+  /// A follow-up is only shown after the notification it follows.
+  pub fn schedule(&self) -> Result<Trigger, cron::error::Error> {
+    let schedule = match self.at {
+      Some(at) => at_schedule(at),
+      None if self.follow_up.is_some() => return Ok(Trigger::Never),
+      None if self.within.is_some() && self.cron.trim().is_empty() => "0 0 0 * * * *".parse(),
+      None => self.cron.parse(),
+    };
+    schedule.map(Trigger::from)
+  }
+
+  /// The key of the notification in the saved state, the label prefixed with
+  /// the file it was merged in from so notifications with the same label in
+  /// different files are kept apart.
+  pub fn state_key(&self) -> String {
+    match &self.source {
+      Some(source) => format!("{}: {}", source.display(), self.label),
+      None => self.label.clone(),
+    }
+  }
+
+  /// The key of the notification's shown count in the saved state. A
+  /// reminder with `at` includes the time so a new reminder with the same
+  /// label doesn't count as shown already.
+  pub(crate) fn shown_key(&self) -> String {
+    match self.at {
+      Some(at) => format!("{} at {}", self.state_key(), at.format("%Y-%m-%dT%H:%M:%S")),
+      None => self.state_key(),
+    }
+  }
+
+  /// The most times the notification is shown, a reminder with `at` is shown
+  /// once.
+  pub fn limit(&self) -> Option<u32> {
+    match self.at {
+      Some(_) => Some(1),
+      None => self.max_occurrences,
+    }
+  }
+
+  /// What to do when the notification is due during quiet hours.
+  pub fn quiet_policy(&self) -> QuietPolicy {
+    self.quiet.unwrap_or(self.level.quiet_policy())
+  }
+}
+
+#[derive(Debug, Error)]
+pub enum YamlErrors {
+  #[error("Error trying to read the config file")]
+  CouldNotReadConfigFile,
+  #[error("The config file is invalid: {0}")]
+  CouldNotDeserializeFile(String),
+  #[error("Error trying to save to the config file")]
+  CouldNotSaveToFile,
+  #[error("The notification is defined in an included config file")]
+  NotInConfigFile,
+  #[error("Saving would rewrite the whole config file and lose its comments and formatting")]
+  WouldReformat,
+  #[error("The config file is version {0} which is newer than this version of notifier supports")]
+  NewerVersion(u32),
+}
+
+pub fn load_contents(path: &PathBuf) -> Result<String, YamlErrors> {
+  std::fs::read_to_string(path).map_err(|_| YamlErrors::CouldNotReadConfigFile)
+}
+
+/// Deserialises the config, migrating it to the current version if needed.
+pub fn deserialise(content: &str, format: Format) -> Result<Notifications, YamlErrors> {
+  if content.trim().is_empty() {
+    return Ok(Notifications::default());
+  }
+  let mut doc = format.parse(content)?;
+  let version = migrations::migrate(&mut doc);
+  if version > CURRENT_VERSION {
+    tracing::warn!(
+      version,
+      supported = CURRENT_VERSION,
+      "The config file is newer than supported, newer settings are ignored"
+    );
+  }
+  serde_yaml::from_value(doc).map_err(crate::codec::invalid)
+}
+
+pub fn load_file_and_deserialise(path: &PathBuf) -> Result<Notifications, YamlErrors> {
+  let config_content = load_contents(path)?;
+  deserialise(&config_content, Format::from_path(path)).inspect_err(|err| {
+    tracing::error!(path = %path.display(), "{}", err);
+  })
+}
+
+/// Writes the whole config to `path` in the format matching its extension.
+pub fn save_contents(path: &PathBuf, notify: &Notifications) -> Result<(), YamlErrors> {
+  fs::write(path, Format::from_path(path).serialise(notify)?)
+    .map_err(|_| YamlErrors::CouldNotSaveToFile)?;
+  Ok(())
+}
+
+/// Rewrites the whole config file with the notifications defined in it,
+/// dropping its comments and formatting. Only for when the user agreed to it
+/// after [`save_edit`] returned [`YamlErrors::WouldReformat`].
+pub fn save_reformatted(path: &PathBuf, notify: &Notifications) -> Result<(), YamlErrors> {
+  save_contents(path, &notify.own())
+}
+
+/// Reads the config at `from` and writes it to `to`, converting between the
+/// formats of their extensions. Comments are not carried over.
+pub fn convert(from: &PathBuf, to: &PathBuf) -> Result<(), YamlErrors> {
+  let notify = load_file_and_deserialise(from)?;
+  if notify.is_newer_than_supported() {
+    return Err(YamlErrors::NewerVersion(notify.version));
+  }
+  save_contents(to, &notify)
+}
+
+/// A change to a single notification, used to only rewrite the affected part
+/// of the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigEdit {
+  /// The last notification was added
+  Add,
+  /// The notification at the index was changed
+  Update(usize),
+  /// The notification at the index was removed
+  Remove(usize),
+  /// The last countdown was added
+  AddCountdown,
+  /// The countdown at the index was removed
+  RemoveCountdown(usize),
+  /// The last routine was added
+  AddRoutine,
+  /// The routine at the index was removed
+  RemoveRoutine(usize),
+}
+
+fn same_notifications(a: &[NotificationDetails], b: &[NotificationDetails]) -> bool {
+  let as_saved = |n: &[NotificationDetails]| {
+    n.iter()
+      .cloned()
+      .map(|mut n| {
+        n.job_id = None;
+        n.excluded = Calendar::default();
+        n
+      })
+      .collect::<Vec<_>>()
+  };
+  as_saved(a) == as_saved(b)
+}
+
+/// The item as a YAML mapping to write into the config file.
+fn mapping<T: Serialize>(item: Option<&T>) -> Option<Mapping> {
+  match serde_yaml::to_value(item?) {
+    Ok(Value::Mapping(mapping)) => Some(mapping),
+    _ => None,
+  }
+}
+
+fn edit_contents(content: &str, notify: &Notifications, edit: ConfigEdit) -> Option<String> {
+  let edited = match edit {
+    ConfigEdit::Add => crate::yaml_edit::push_item(
+      content,
+      "notifications",
+      &mapping(notify.notifications.last())?,
+    )?,
+    ConfigEdit::Update(index) => crate::yaml_edit::update_item(
+      content,
+      "notifications",
+      index,
+      &mapping(notify.notifications.get(index))?,
+    )?,
+    ConfigEdit::Remove(index) => crate::yaml_edit::remove_item(content, "notifications", index)?,
+    ConfigEdit::AddCountdown => {
+      crate::yaml_edit::push_item(content, "countdowns", &mapping(notify.countdowns.last())?)?
+    }
+    ConfigEdit::RemoveCountdown(index) => {
+      crate::yaml_edit::remove_item(content, "countdowns", index)?
+    }
+    ConfigEdit::AddRoutine => {
+      crate::yaml_edit::push_item(content, "routines", &mapping(notify.routines.last())?)?
+    }
+    ConfigEdit::RemoveRoutine(index) => crate::yaml_edit::remove_item(content, "routines", index)?,
+  };
+  let reparsed = deserialise(&edited, Format::Yaml).ok()?;
+  (same_notifications(&reparsed.notifications, &notify.notifications)
+    && reparsed.countdowns == notify.countdowns
+    && reparsed.routines == notify.routines)
+    .then_some(edited)
+}
+
+/// Saves `notify`, which already has `edit` applied, to the config file.
+///
+/// Only the lines of the changed notification are rewritten so comments and
+/// formatting in the rest of the file are kept, this is only supported for
+/// YAML files. If the file can't be edited in place it is left alone and
+/// [`YamlErrors::WouldReformat`] is returned, see [`save_reformatted`].
+pub fn save_edit(
+  path: &PathBuf,
+  notify: &Notifications,
+  edit: ConfigEdit,
+) -> Result<(), YamlErrors> {
+  let edit = match edit {
+    ConfigEdit::Update(index)
+      if notify
+        .notifications
+        .get(index)
+        .is_some_and(|n| n.source.is_some()) =>
+    {
+      return Err(YamlErrors::NotInConfigFile)
+    }
+    ConfigEdit::Update(index) => ConfigEdit::Update(own_index(&notify.notifications, index)),
+    ConfigEdit::Remove(index) => ConfigEdit::Remove(own_index(&notify.notifications, index)),
+    ConfigEdit::RemoveCountdown(index) => {
+      ConfigEdit::RemoveCountdown(own_index(&notify.countdowns, index))
+    }
+    ConfigEdit::RemoveRoutine(index) => {
+      ConfigEdit::RemoveRoutine(own_index(&notify.routines, index))
+    }
+    edit @ (ConfigEdit::Add | ConfigEdit::AddCountdown | ConfigEdit::AddRoutine) => edit,
+  };
+  let notify = &notify.own();
+  let content = load_contents(path).unwrap_or_default();
+  if content.trim().is_empty() {
+    return save_contents(path, notify);
+  }
+  let edited = match Format::from_path(path) {
+    Format::Yaml => edit_contents(&content, notify, edit),
+    Format::Toml | Format::Json => None,
+  };
+  match edited {
+    Some(edited) => fs::write(path, edited).map_err(|_| YamlErrors::CouldNotSaveToFile),
+    None => Err(YamlErrors::WouldReformat),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::{TimeZone, Utc};
+
+  use super::*;
+
+  #[test]
+  fn test_edits_routines_in_place() {
+    let content = "# Mine\nnotifications:\n- label: Water\n  cron: 0 0 9 * * * *\n";
+    let mut notify = deserialise(content, Format::Yaml).unwrap();
+    notify.routines.push(Routine {
+      name: "Stretch".to_string(),
+      steps: vec![RoutineStep::default()],
+      ..Default::default()
+    });
+    let edited = edit_contents(content, &notify, ConfigEdit::AddRoutine).unwrap();
+    assert!(edited.starts_with(content), "{}", edited);
+    assert_eq!(
+      deserialise(&edited, Format::Yaml).unwrap().routines,
+      notify.routines
+    );
+
+    notify.routines.clear();
+    let removed = edit_contents(&edited, &notify, ConfigEdit::RemoveRoutine(0)).unwrap();
+    assert!(removed.starts_with(content), "{}", removed);
+  }
+
+  #[test]
+  fn test_does_not_reformat_the_file() {
+    let path =
+      std::env::temp_dir().join(format!("notifier-reformat-{}.toml", uuid::Uuid::new_v4()));
+    let content = "# Mine\n[[notifications]]\nlabel = \"Water\"\ncron = \"0 0 9 * * * *\"\n";
+    fs::write(&path, content).unwrap();
+    let mut notify = load_file_and_deserialise(&path).unwrap();
+    notify.notifications[0].label = "Tea".to_string();
+    assert!(matches!(
+      save_edit(&path, &notify, ConfigEdit::Update(0)),
+      Err(YamlErrors::WouldReformat)
+    ));
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+    save_reformatted(&path, &notify).unwrap();
+    let saved = load_file_and_deserialise(&path).unwrap();
+    assert_eq!(saved.notifications[0].label, "Tea");
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_countdown() {
+    let countdown: Countdown =
+      serde_yaml::from_str("label: Holiday\ndate: 2026-12-20\nmilestones: [7, 0, -3]\n").unwrap();
+    let today = NaiveDate::from_ymd_opt(2026, 12, 1).unwrap();
+    assert_eq!(countdown.days_left(today), 19);
+    assert_eq!(countdown.next_milestone(today), Some(7));
+    assert_eq!(countdown.next_milestone(countdown.date), Some(0));
+    assert_eq!(
+      countdown.next_milestone(countdown.date.succ_opt().unwrap()),
+      Some(-3)
+    );
+    assert_eq!(countdown.text(-3), "Holiday was 3 days ago");
+
+    let milestones = countdown.milestones().unwrap();
+    let after = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    let (schedule, text) = &milestones[0];
+    assert_eq!(text, "Holiday in 7 days");
+    assert_eq!(
+      schedule.after(&after).next(),
+      Some(Utc.with_ymd_and_hms(2026, 12, 13, 9, 0, 0).unwrap())
+    );
+    assert_eq!(milestones[1].1, "Holiday is today");
+  }
+
+  #[test]
+  fn test_one_off_reminder_schedule() {
+    let notification: NotificationDetails =
+      serde_yaml::from_str("label: Call the dentist\nat: 2026-11-03T14:00:00\n").unwrap();
+    assert_eq!(notification.limit(), Some(1));
+    let Ok(Trigger::Schedule(schedule)) = notification.schedule() else {
+      panic!("a reminder has a schedule");
+    };
+    let after = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(
+      schedule.after(&after).collect::<Vec<_>>(),
+      vec![Utc.with_ymd_and_hms(2026, 11, 3, 14, 0, 0).unwrap()]
+    );
+    assert_eq!(
+      serde_yaml::to_string(&notification).unwrap(),
+      "label: Call the dentist\nat: 2026-11-03T14:00:00\nlevel: Info\n"
+    );
+  }
+}
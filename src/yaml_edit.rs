@@ -0,0 +1,531 @@
+//! Format preserving edits of a YAML document.
+//!
+//! Re-serialising the whole config with serde_yaml drops any comments and
+//! hand written formatting. The functions in this module only rewrite the
+//! lines that belong to the sequence item being added, updated or removed and
+//! leave the rest of the document untouched.
+//!
+//! Only block style sequences of block style mappings are supported, e.g.
+//!
+//! ```yaml
+//! notifications:
+//!   # Comments are kept
+//!   - label: Stretch # so are trailing comments
+//!     cron: 0 0 7-15 * * 1-5 *
+//! ```
+//!
+//! Anything else returns `None` so the caller can fall back to a full rewrite.
+
+use serde_yaml::{Mapping, Value};
+
+struct Document<'a> {
+  lines: Vec<&'a str>,
+  newline: &'static str,
+}
+
+/// The lines of a single item in a block sequence.
+struct Item {
+  /// First line of the comments directly above the item.
+  comment_start: usize,
+  /// The line with the `-` indicator.
+  start: usize,
+  /// One past the last non blank, non comment line of the item.
+  end: usize,
+  /// Column the keys of the item's mapping start at.
+  map_indent: usize,
+}
+
+/// The lines of a key and its value within an item.
+struct Entry {
+  key: String,
+  start: usize,
+  end: usize,
+}
+
+/// A list of line replacements that are applied from the bottom up.
+type Edits = Vec<(usize, usize, Vec<String>)>;
+
+fn indent(line: &str) -> usize {
+  line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_content(line: &str) -> bool {
+  let trimmed = line.trim();
+  !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+fn is_item_start(line: &str) -> bool {
+  let trimmed = line.trim_start_matches(' ');
+  trimmed == "-" || trimmed.starts_with("- ")
+}
+
+/// Splits `key: value # comment` into the key and the byte range of the value.
+fn split_entry(text: &str) -> Option<(&str, usize, usize)> {
+  let colon = text.find(": ").or_else(|| {
+    let trimmed = text.trim_end();
+    trimmed.ends_with(':').then(|| trimmed.len() - 1)
+  })?;
+  let key = &text[..colon];
+  if key.is_empty() || key.starts_with(['#', '-', '[', '{']) {
+    return None;
+  }
+  let value_start = colon + 1 + (text[colon + 1..].len() - text[colon + 1..].trim_start().len());
+  let mut quote = None;
+  let mut value_end = text.len();
+  let mut previous = ' ';
+  for (i, c) in text[value_start..].char_indices() {
+    match (quote, c) {
+      (None, '\'' | '"') => quote = Some(c),
+      (Some(q), _) if q == c => quote = None,
+      (None, '#') if previous == ' ' => {
+        value_end = value_start + i;
+        break;
+      }
+      _ => {}
+    }
+    previous = c;
+  }
+  let value_end = value_start + text[value_start..value_end].trim_end().len();
+  Some((key.trim_end(), value_start, value_end))
+}
+
+/// Renders `key: value` as block YAML indented by `indent` columns.
+fn render_entry(key: &str, value: &Value, indent: usize) -> Option<Vec<String>> {
+  let mut mapping = Mapping::new();
+  mapping.insert(Value::String(key.to_string()), value.clone());
+  render_mapping(&mapping, indent, indent)
+}
+
+/// Renders a mapping as block YAML, the first line is indented by `first` and
+/// the rest by `rest` columns.
+fn render_mapping(mapping: &Mapping, first: usize, rest: usize) -> Option<Vec<String>> {
+  let rendered = serde_yaml::to_string(mapping).ok()?;
+  Some(
+    rendered
+      .lines()
+      .enumerate()
+      .map(|(i, line)| {
+        let pad = if i == 0 { first } else { rest };
+        format!("{}{}", " ".repeat(pad), line)
+      })
+      .collect(),
+  )
+}
+
+/// Renders a scalar so it can replace a value in place.
+fn render_scalar(value: &Value) -> Option<String> {
+  if value.is_mapping() || value.is_sequence() {
+    return None;
+  }
+  let rendered = serde_yaml::to_string(value).ok()?;
+  let rendered = rendered.trim_end();
+  (!rendered.contains('\n')).then(|| rendered.to_string())
+}
+
+impl<'a> Document<'a> {
+  fn new(doc: &'a str) -> Self {
+    Self {
+      lines: doc.lines().collect(),
+      newline: if doc.contains("\r\n") { "\r\n" } else { "\n" },
+    }
+  }
+
+  fn apply(&self, mut edits: Edits) -> String {
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.0));
+    let mut lines: Vec<String> = self.lines.iter().map(|l| l.to_string()).collect();
+    for (start, end, replacement) in edits {
+      lines.splice(start..end, replacement);
+    }
+    let mut doc = lines.join(self.newline);
+    doc.push_str(self.newline);
+    doc
+  }
+
+  /// Finds the top level `key:` line.
+  fn section(&self, key: &str) -> Option<usize> {
+    self.lines.iter().position(|line| {
+      line
+        .strip_prefix(key)
+        .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+    })
+  }
+
+  /// One past the last content line of the section starting at `section`.
+  fn section_end(&self, section: usize) -> usize {
+    let mut end = section + 1;
+    for (i, line) in self.lines.iter().enumerate().skip(section + 1) {
+      if !is_content(line) {
+        continue;
+      }
+      if indent(line) == 0 && !is_item_start(line) {
+        break;
+      }
+      end = i + 1;
+    }
+    end
+  }
+
+  fn items(&self, section: usize) -> Option<Vec<Item>> {
+    let end = self.section_end(section);
+    let mut starts = Vec::new();
+    let mut item_indent = None;
+    for i in section + 1..end {
+      let line = self.lines[i];
+      if !is_content(line) {
+        continue;
+      }
+      match item_indent {
+        None if is_item_start(line) => item_indent = Some(indent(line)),
+        None => return None,
+        Some(_) => {}
+      }
+      if is_item_start(line) && Some(indent(line)) == item_indent {
+        starts.push(i);
+      }
+    }
+    let mut items = Vec::new();
+    for (n, &start) in starts.iter().enumerate() {
+      let next = starts.get(n + 1).copied().unwrap_or(end);
+      let mut item_end = start + 1;
+      for i in start + 1..next {
+        if is_content(self.lines[i]) {
+          item_end = i + 1;
+        }
+      }
+      let mut comment_start = start;
+      while comment_start > section + 1 && self.lines[comment_start - 1].trim().starts_with('#') {
+        comment_start -= 1;
+      }
+      let line = self.lines[start];
+      let after_dash = &line[indent(line) + 1..];
+      let map_indent = if after_dash.trim().is_empty() {
+        (start + 1..item_end)
+          .find(|&i| is_content(self.lines[i]))
+          .map(|i| indent(self.lines[i]))?
+      } else {
+        line.len() - after_dash.trim_start().len()
+      };
+      items.push(Item {
+        comment_start,
+        start,
+        end: item_end,
+        map_indent,
+      });
+    }
+    Some(items)
+  }
+
+  fn entries(&self, item: &Item) -> Option<Vec<Entry>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for i in item.start..item.end {
+      let line = self.lines[i];
+      if !is_content(line) || (i == item.start && line.trim() == "-") {
+        continue;
+      }
+      if i == item.start || indent(line) == item.map_indent {
+        let (key, _, _) = split_entry(line.get(item.map_indent..)?)?;
+        entries.push(Entry {
+          key: key.to_string(),
+          start: i,
+          end: i + 1,
+        });
+      } else if indent(line) > item.map_indent {
+        entries.last_mut()?.end = i + 1;
+      } else {
+        return None;
+      }
+    }
+    Some(entries)
+  }
+
+  /// Converts the section's value to an empty block sequence if it is `[]`.
+  fn empty_flow_sequence(&self, section: usize) -> Option<Edits> {
+    let line = self.lines[section];
+    let (key, value_start, value_end) = split_entry(line)?;
+    match &line[value_start..value_end] {
+      "" => Some(Vec::new()),
+      "[]" | "~" | "null" => {
+        let comment = line[value_end..].trim();
+        let replacement = if comment.is_empty() {
+          format!("{}:", key)
+        } else {
+          format!("{}: {}", key, comment)
+        };
+        Some(vec![(section, section + 1, vec![replacement])])
+      }
+      _ => None,
+    }
+  }
+}
+
+/// Appends `item` to the sequence under the top level `key`.
+pub fn push_item(doc: &str, key: &str, item: &Mapping) -> Option<String> {
+  let document = Document::new(doc);
+  let Some(section) = document.section(key) else {
+    let mut doc = doc.to_string();
+    if !doc.is_empty() && !doc.ends_with('\n') {
+      doc.push_str(document.newline);
+    }
+    doc.push_str(&format!("{}:{}", key, document.newline));
+    return push_item(&doc, key, item);
+  };
+  let items = document.items(section)?;
+  let mut edits = Vec::new();
+  let (item_indent, at) = match items.last() {
+    Some(last) => (indent(document.lines[last.start]), last.end),
+    None => {
+      edits = document.empty_flow_sequence(section)?;
+      (2, document.section_end(section))
+    }
+  };
+  let lines = render_mapping(item, item_indent, item_indent + 2)?;
+  let lines = lines
+    .into_iter()
+    .enumerate()
+    .map(|(i, line)| {
+      if i == 0 {
+        format!("{}- {}", " ".repeat(item_indent), line.trim_start())
+      } else {
+        line
+      }
+    })
+    .collect();
+  edits.push((at, at, lines));
+  Some(document.apply(edits))
+}
+
+/// Replaces the item at `index` of the sequence under the top level `key`.
+///
+/// Keys whose value did not change are left as is, changed scalars are
+/// replaced in place (keeping any trailing comment), new keys are appended to
+/// the end of the item and removed keys are dropped.
+pub fn update_item(doc: &str, key: &str, index: usize, item: &Mapping) -> Option<String> {
+  let parsed: Value = serde_yaml::from_str(doc).ok()?;
+  let old = parsed.get(key)?.get(index)?.as_mapping()?;
+  let document = Document::new(doc);
+  let items = document.items(document.section(key)?)?;
+  let target = items.get(index)?;
+  let entries = document.entries(target)?;
+  let prefix = &document.lines[target.start][..target.map_indent];
+
+  let mut edits = Vec::new();
+  let mut removed_first = false;
+  for entry in entries.iter() {
+    let name = Value::String(entry.key.clone());
+    let Some(new_value) = item.get(&name) else {
+      removed_first |= entry.start == target.start;
+      edits.push((entry.start, entry.end, Vec::new()));
+      continue;
+    };
+    if old.get(&name) == Some(new_value) {
+      continue;
+    }
+    let line = document.lines[entry.start];
+    let is_scalar = entry.end == entry.start + 1;
+    let replacement = match (is_scalar, render_scalar(new_value)) {
+      (true, Some(scalar)) => {
+        let (_, start, end) = split_entry(line.get(target.map_indent..)?)?;
+        let (start, end) = (start + target.map_indent, end + target.map_indent);
+        let pad = if start == end && end == line.len() {
+          " "
+        } else {
+          ""
+        };
+        vec![format!(
+          "{}{}{}{}",
+          &line[..start],
+          pad,
+          scalar,
+          &line[end..]
+        )]
+      }
+      _ => {
+        let mut lines = render_entry(&entry.key, new_value, target.map_indent)?;
+        if entry.start == target.start {
+          lines[0] = format!("{}{}", prefix, lines[0].trim_start());
+        }
+        lines
+      }
+    };
+    edits.push((entry.start, entry.end, replacement));
+  }
+
+  if removed_first {
+    let next = entries
+      .iter()
+      .find(|e| item.contains_key(Value::String(e.key.clone())))?;
+    let edit = edits.iter_mut().find(|e| e.0 == next.start);
+    let line = match edit {
+      Some((_, _, lines)) => lines.first_mut()?,
+      None => {
+        edits.push((
+          next.start,
+          next.start + 1,
+          vec![document.lines[next.start].to_string()],
+        ));
+        edits.last_mut()?.2.first_mut()?
+      }
+    };
+    *line = format!("{}{}", prefix, line.trim_start());
+  }
+
+  let mut added = Mapping::new();
+  for (name, value) in item {
+    if !entries
+      .iter()
+      .any(|e| Some(e.key.as_str()) == name.as_str())
+    {
+      added.insert(name.clone(), value.clone());
+    }
+  }
+  if !added.is_empty() {
+    let lines = render_mapping(&added, target.map_indent, target.map_indent)?;
+    edits.push((target.end, target.end, lines));
+  }
+  Some(document.apply(edits))
+}
+
+/// Removes the item at `index` of the sequence under the top level `key`
+/// together with the comments directly above it.
+pub fn remove_item(doc: &str, key: &str, index: usize) -> Option<String> {
+  let document = Document::new(doc);
+  let items = document.items(document.section(key)?)?;
+  let target = items.get(index)?;
+  let mut end = target.end;
+  let blank_before = index == 0 || document.lines[target.comment_start - 1].trim().is_empty();
+  let blank_after = document
+    .lines
+    .get(end)
+    .is_none_or(|line| line.trim().is_empty());
+  if blank_before && blank_after && end < document.lines.len() {
+    end += 1;
+  }
+  Some(document.apply(vec![(target.comment_start, end, Vec::new())]))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DOC: &str = "---
+# Shared reminders
+notifications:
+  # Stand up every hour
+  - label: Stretch # keep short
+    cron: 0 0 7-15 * * 1-5 *
+    level: Info
+
+  - label: Drink water
+    cron: '0 5 7-15 * * 1-5 *'
+    level: Info
+# trailing comment
+";
+
+  fn mapping(label: &str, cron: &str) -> Mapping {
+    let mut mapping = Mapping::new();
+    mapping.insert("label".into(), label.into());
+    mapping.insert("cron".into(), cron.into());
+    mapping.insert("level".into(), "Info".into());
+    mapping
+  }
+
+  #[test]
+  fn test_update_keeps_comments() {
+    let doc = update_item(
+      DOC,
+      "notifications",
+      0,
+      &mapping("Walk", "0 0 7-15 * * 1-5 *"),
+    )
+    .unwrap();
+    assert_eq!(doc, DOC.replace("Stretch #", "Walk #"));
+  }
+
+  #[test]
+  fn test_update_keeps_quoting_of_unchanged_values() {
+    let doc = update_item(
+      DOC,
+      "notifications",
+      1,
+      &mapping("Tea", "0 5 7-15 * * 1-5 *"),
+    )
+    .unwrap();
+    assert_eq!(doc, DOC.replace("Drink water", "Tea"));
+  }
+
+  #[test]
+  fn test_update_adds_and_removes_keys() {
+    let mut item = mapping("Stretch", "0 0 7-15 * * 1-5 *");
+    item.remove("label");
+    item.insert("extra".into(), vec!["a", "b"].into());
+    let doc = update_item(DOC, "notifications", 0, &item).unwrap();
+    let expected = DOC.replace(
+      "  - label: Stretch # keep short\n    cron: 0 0 7-15 * * 1-5 *\n    level: Info\n",
+      "  - cron: 0 0 7-15 * * 1-5 *\n    level: Info\n    extra:\n    - a\n    - b\n",
+    );
+    assert_eq!(doc, expected);
+  }
+
+  #[test]
+  fn test_push_appends_after_last_item() {
+    let doc = push_item(DOC, "notifications", &mapping("Eat", "0 0 12 * * * *")).unwrap();
+    let expected = DOC.replace(
+      "# trailing comment",
+      "  - label: Eat\n    cron: 0 0 12 * * * *\n    level: Info\n# trailing comment",
+    );
+    assert_eq!(doc, expected);
+  }
+
+  #[test]
+  fn test_push_into_empty_sequence() {
+    let doc = push_item(
+      "notifications: [] # none yet\n",
+      "notifications",
+      &mapping("Eat", "1"),
+    )
+    .unwrap();
+    assert_eq!(
+      doc,
+      "notifications: # none yet\n  - label: Eat\n    cron: '1'\n    level: Info\n"
+    );
+  }
+
+  #[test]
+  fn test_remove_drops_item_and_its_comments() {
+    let doc = remove_item(DOC, "notifications", 0).unwrap();
+    let expected = DOC.replace(
+      "  # Stand up every hour\n  - label: Stretch # keep short\n    cron: 0 0 7-15 * * 1-5 *\n    level: Info\n\n",
+      "",
+    );
+    assert_eq!(doc, expected);
+  }
+
+  #[test]
+  fn test_zero_indented_sequence() {
+    let doc = "notifications:\n- label: A\n  cron: x\n  level: Info\nother: 1\n";
+    let doc = update_item(doc, "notifications", 0, &mapping("B", "x")).unwrap();
+    assert_eq!(
+      doc,
+      "notifications:\n- label: B\n  cron: x\n  level: Info\nother: 1\n"
+    );
+    let doc = push_item(&doc, "notifications", &mapping("C", "y")).unwrap();
+    assert_eq!(
+      doc,
+      "notifications:\n- label: B\n  cron: x\n  level: Info\n- label: C\n  cron: y\n  level: Info\nother: 1\n"
+    );
+  }
+
+  #[test]
+  fn test_keeps_crlf() {
+    let doc = DOC.replace('\n', "\r\n");
+    let updated = update_item(
+      &doc,
+      "notifications",
+      0,
+      &mapping("Walk", "0 0 7-15 * * 1-5 *"),
+    )
+    .unwrap();
+    assert_eq!(updated, doc.replace("Stretch #", "Walk #"));
+  }
+}